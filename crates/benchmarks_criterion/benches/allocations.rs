@@ -0,0 +1,248 @@
+//! Counts the heap allocations of steady-state steps, which should be none:
+//! every buffer a step needs belongs to the cache of its step algorithm.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ivp::*;
+use ivp_examples::*;
+use nalgebra::*;
+use num_traits::Float;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Steps taken before counting, so that e.g. a sparse LU has seen its fill
+const WARMUP: usize = 20;
+const STEPS: usize = 200;
+
+/// Step through [`Integrator::step`], saving only the end so that steps have
+/// nothing to keep, and return the number of allocations per attempted step
+/// after the warmup
+fn allocations_per_step<F, S, SA, AS>(
+    prob: &OdeProblem<F, S>,
+    step_algorithm: &SA,
+    adaptive_strategy: &AS,
+) -> f64
+where
+    F: Float + Scalar,
+    S: OdeSystem<F>,
+    SA: StepAlgorithm<F>,
+    SA::Jacobian: JacobianOf<F, S>,
+    AS: AdaptiveStrategy<F, SA::ErrorEstimate>,
+{
+    let opts = SolveOptions {
+        saveat: SaveAt::End,
+        dense: false,
+        ..Default::default()
+    };
+    let mut integrator = prob.init(step_algorithm, adaptive_strategy, opts);
+    for _ in 0..WARMUP {
+        integrator.step().unwrap();
+    }
+    assert!(
+        !integrator.finished(),
+        "the time span ended during the warmup"
+    );
+    let (mut allocations, mut attempted) = (0, 0);
+    for _ in 0..STEPS {
+        let (before, iters) = (ALLOCATIONS.load(Ordering::Relaxed), integrator.iters());
+        integrator.step().unwrap();
+        // The last step saves the end
+        if integrator.finished() {
+            break;
+        }
+        allocations += ALLOCATIONS.load(Ordering::Relaxed) - before;
+        attempted += integrator.iters() - iters;
+    }
+    allocations as f64 / attempted as f64
+}
+
+fn check(name: &str, allocations: f64) -> bool {
+    println!("{name:<45} {allocations:>8.2} allocations/step");
+    allocations == 0.
+}
+
+fn main() {
+    let mut ok = true;
+
+    let prob = lotka_volterra::create_prob();
+    let controller = IntegralController::new(1e-8, 1e-8, 4);
+    ok &= check(
+        "lotka-volterra tsit5",
+        allocations_per_step(&prob, &Tsit5, &controller),
+    );
+    ok &= check(
+        "lotka-volterra dp5",
+        allocations_per_step(&prob, &DP5, &controller),
+    );
+    ok &= check(
+        "lotka-volterra euler",
+        allocations_per_step(&prob, &Euler, &ConstantStep(1e-3)),
+    );
+
+    // Lotka-Volterra has neither a Jacobian nor a tgrad of its own, so these
+    // are the forward differences that OdeSystem falls back on
+    ok &= check(
+        "lotka-volterra rosenbrock23",
+        allocations_per_step(
+            &prob,
+            &Rosenbrock23::new(),
+            &IntegralController::new(1e-6, 1e-6, 2),
+        ),
+    );
+    ok &= check(
+        "lotka-volterra rosenbrock23 gmres",
+        allocations_per_step(
+            &prob,
+            &Rosenbrock23::new().with_linear_solver(Gmres::new()),
+            &IntegralController::new(1e-6, 1e-6, 2),
+        ),
+    );
+
+    let prob = pleiades::create_prob();
+    ok &= check(
+        "pleiades tsit5",
+        allocations_per_step(&prob, &Tsit5, &IntegralController::new(1e-8, 1e-8, 4)),
+    );
+    ok &= check(
+        "pleiades vern7",
+        allocations_per_step(&prob, &Vern7, &IntegralController::new(1e-8, 1e-8, 6)),
+    );
+    ok &= check(
+        "pleiades vern9",
+        allocations_per_step(&prob, &Vern9, &IntegralController::new(1e-8, 1e-8, 8)),
+    );
+    ok &= check(
+        "pleiades cash-karp",
+        allocations_per_step(
+            &prob,
+            &ExplicitRK::new(ButcherTableau::cash_karp()),
+            &ProportionalIntegralController::new(1e-8, 1e-8, 4),
+        ),
+    );
+    ok &= check(
+        "pleiades bs3",
+        allocations_per_step(
+            &prob,
+            &ExplicitRK::new(ButcherTableau::bs3()),
+            &IntegralController::new(1e-6, 1e-6, 2),
+        ),
+    );
+
+    // Rober has an analytic dense Jacobian, but none for the banded solver
+    let prob = rober::create_prob();
+    let controller = IntegralController::new(1e-6, 1e-8, 2);
+    ok &= check(
+        "rober rosenbrock23",
+        allocations_per_step(&prob, &Rosenbrock23::new(), &controller),
+    );
+    ok &= check(
+        "rober rosenbrock23 banded",
+        allocations_per_step(
+            &prob,
+            &Rosenbrock23::new()
+                .with_jacobian(FiniteDifference::Forward)
+                .with_linear_solver(BandedSolver::new(2, 2)),
+            &controller,
+        ),
+    );
+    ok &= check(
+        "rober rosenbrock23 reuse",
+        allocations_per_step(
+            &prob,
+            &Rosenbrock23::new().with_jacobian_reuse(JacobianReuse::new(20, 2.)),
+            &controller,
+        ),
+    );
+
+    let prob = brusselator::create_prob(100);
+    let controller = IntegralController::new(1e-6, 1e-6, 2);
+    ok &= check(
+        "brusselator rosenbrock23",
+        allocations_per_step(&prob, &Rosenbrock23::new(), &controller),
+    );
+    ok &= check(
+        "brusselator rosenbrock23 dense",
+        allocations_per_step(
+            &prob,
+            &Rosenbrock23::new()
+                .with_jacobian(FiniteDifference::Forward)
+                .with_linear_solver(DenseSolver::new()),
+            &controller,
+        ),
+    );
+    ok &= check(
+        "brusselator rosenbrock23 sparse",
+        allocations_per_step(
+            &prob,
+            &Rosenbrock23::new().with_jacobian(FiniteDifference::Forward),
+            &controller,
+        ),
+    );
+    ok &= check(
+        "brusselator rosenbrock23 sparse ad",
+        allocations_per_step(
+            &prob,
+            &Rosenbrock23::new().with_jacobian(ForwardDiff),
+            &controller,
+        ),
+    );
+    ok &= check(
+        "brusselator rosenbrock23 banded central",
+        allocations_per_step(
+            &prob,
+            &Rosenbrock23::new()
+                .with_jacobian(FiniteDifference::Central)
+                .with_linear_solver(BandedSolver::new(2, 2)),
+            &controller,
+        ),
+    );
+    ok &= check(
+        "brusselator rosenbrock23 gmres",
+        allocations_per_step(
+            &prob,
+            &Rosenbrock23::new()
+                .with_jacobian(FiniteDifference::Forward)
+                .with_linear_solver(Gmres::new()),
+            &controller,
+        ),
+    );
+    ok &= check(
+        "brusselator rosenbrock23 gmres banded",
+        allocations_per_step(
+            &prob,
+            &Rosenbrock23::new()
+                .with_jacobian(ForwardDiff)
+                .with_linear_solver(Gmres::new().with_preconditioner(BandedSolver::new(2, 2))),
+            &controller,
+        ),
+    );
+
+    assert!(ok, "steady-state steps allocated");
+}
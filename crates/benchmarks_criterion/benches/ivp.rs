@@ -10,7 +10,7 @@ static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 fn bench_ivp<
     F: Float + Scalar + 'static,
     S: OdeSystem<F>,
-    SA: StepAlgorithm<F>,
+    SA: StepAlgorithm<F, Jacobian: JacobianOf<F, S>> + Clone,
     AS: AdaptiveStrategy<F, SA::ErrorEstimate>,
 >(
     name: &str,
@@ -21,7 +21,7 @@ fn bench_ivp<
 ) {
     c.bench_function(name, |b| {
         b.iter(|| {
-            prob.solve(&step_algorithm, &adaptive_strategy);
+            prob.solve(&step_algorithm, &adaptive_strategy).unwrap();
         })
     });
 }
@@ -32,23 +32,121 @@ fn ivp(c: &mut Criterion) {
         c,
         lotka_volterra::create_prob(),
         Tsit5,
-        IntegralController::new(0.1, 1e-3, 1e-6, 4),
+        IntegralController::new(1e-3, 1e-6, 4),
     );
 
-    // bench_ivp(
-    //     "lotka-volterra rosenbrock23",
-    //     c,
-    //     lotka_volterra::create_prob(),
-    //     Rosenbrock23,
-    //     AdaptiveStep::new(0.1, 1e-3, 1e-6, 4),
-    // );
+    bench_ivp(
+        "lotka-volterra tsit5 pi",
+        c,
+        lotka_volterra::create_prob(),
+        Tsit5,
+        ProportionalIntegralController::new(1e-3, 1e-6, 4),
+    );
+
+    bench_ivp(
+        "lotka-volterra dp5",
+        c,
+        lotka_volterra::create_prob(),
+        DP5,
+        IntegralController::new(1e-3, 1e-6, 4),
+    );
+
+    bench_ivp(
+        "lotka-volterra rosenbrock23",
+        c,
+        lotka_volterra::create_prob(),
+        Rosenbrock23::new(),
+        IntegralController::new(1e-3, 1e-6, 2),
+    );
 
     bench_ivp(
         "pleiades tsit5",
         c,
         pleiades::create_prob(),
         Tsit5,
-        IntegralController::new(0.1, 1e-3, 1e-6, 4),
+        IntegralController::new(1e-3, 1e-6, 4),
+    );
+
+    bench_ivp(
+        "pleiades tsit5 pi",
+        c,
+        pleiades::create_prob(),
+        Tsit5,
+        ProportionalIntegralController::new(1e-3, 1e-6, 4),
+    );
+
+    bench_ivp(
+        "pleiades dp5",
+        c,
+        pleiades::create_prob(),
+        DP5,
+        IntegralController::new(1e-3, 1e-6, 4),
+    );
+
+    bench_ivp(
+        "pleiades tsit5 tight",
+        c,
+        pleiades::create_prob(),
+        Tsit5,
+        IntegralController::new(1e-10, 1e-10, 4),
+    );
+
+    bench_ivp(
+        "pleiades vern7 tight",
+        c,
+        pleiades::create_prob(),
+        Vern7,
+        IntegralController::new(1e-10, 1e-10, 6),
+    );
+
+    bench_ivp(
+        "pleiades vern9 tight",
+        c,
+        pleiades::create_prob(),
+        Vern9,
+        IntegralController::new(1e-10, 1e-10, 8),
+    );
+
+    bench_ivp(
+        "brusselator rosenbrock23 sparse",
+        c,
+        brusselator::create_prob(100),
+        Rosenbrock23::new(),
+        IntegralController::new(1e-3, 1e-6, 2),
+    );
+
+    bench_ivp(
+        "brusselator rosenbrock23 sparse ad",
+        c,
+        brusselator::create_prob(100),
+        Rosenbrock23::new().with_jacobian(ForwardDiff),
+        IntegralController::new(1e-3, 1e-6, 2),
+    );
+
+    bench_ivp(
+        "brusselator rosenbrock23 banded",
+        c,
+        brusselator::create_prob(100),
+        Rosenbrock23::new().with_linear_solver(BandedSolver::new(2, 2)),
+        IntegralController::new(1e-3, 1e-6, 2),
+    );
+
+    bench_ivp(
+        "brusselator rosenbrock23 banded reuse",
+        c,
+        brusselator::create_prob(100),
+        Rosenbrock23::new()
+            .with_linear_solver(BandedSolver::new(2, 2))
+            .with_jacobian_reuse(JacobianReuse::new(20, 2.)),
+        IntegralController::new(1e-3, 1e-6, 2),
+    );
+
+    bench_ivp(
+        "brusselator rosenbrock23 gmres",
+        c,
+        brusselator::create_prob(100),
+        Rosenbrock23::new().with_linear_solver(Gmres::new()),
+        IntegralController::new(1e-3, 1e-6, 2),
     );
 }
 
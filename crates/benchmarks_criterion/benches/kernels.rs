@@ -0,0 +1,73 @@
+use criterion::*;
+use my_linalg::kernels::*;
+use nalgebra::*;
+
+/// A stage update of a 7 stage Runge-Kutta method, `y1 = y0 + dt Σⱼ aⱼ kⱼ`
+fn bench_stage(
+    name: &str,
+    c: &mut Criterion,
+    f: impl Fn(&[f64; 6], &DMatrix<f64>, DVectorView<f64>, DVectorViewMut<f64>),
+) {
+    static KB: usize = 1024;
+    let a = [0.1, -0.2, 0.3, 0.4, -0.5, 0.6];
+    let mut group = c.benchmark_group(name);
+    for size in [KB, 2 * KB, 4 * KB].iter() {
+        group.throughput(Throughput::Elements(*size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, &size| {
+            b.iter_batched_ref(
+                || {
+                    let ks = DMatrix::<f64>::new_random(size, 7);
+                    let y0 = DVector::<f64>::new_random(size);
+                    let y1 = DVector::<f64>::zeros(size);
+                    (ks, y0, y1)
+                },
+                |(ks, y0, y1)| {
+                    f(&a, ks, y0.as_view(), y1.as_view_mut());
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+}
+
+fn indexed_stage(
+    a: &[f64; 6],
+    ks: &DMatrix<f64>,
+    y0: DVectorView<f64>,
+    mut y1: DVectorViewMut<f64>,
+) {
+    for i in 0..y0.len() {
+        let mut dy = 0.;
+        for (j, aj) in a.iter().enumerate() {
+            dy += aj * ks[(i, j)];
+        }
+        y1[i] = y0[i] + 0.01 * dy;
+    }
+}
+
+fn kernel_stage(
+    a: &[f64; 6],
+    ks: &DMatrix<f64>,
+    y0: DVectorView<f64>,
+    mut y1: DVectorViewMut<f64>,
+) {
+    linear_combination(y1.as_mut_slice(), a, ks.as_slice());
+    axpby(1., y0.as_slice(), 0.01, y1.as_mut_slice());
+}
+
+fn axpy_stage(a: &[f64; 6], ks: &DMatrix<f64>, y0: DVectorView<f64>, mut y1: DVectorViewMut<f64>) {
+    y1.fill(0.);
+    for (j, &aj) in a.iter().enumerate() {
+        axpy(aj, ks.column(j).as_slice(), y1.as_mut_slice());
+    }
+    axpby(1., y0.as_slice(), 0.01, y1.as_mut_slice());
+}
+
+pub fn stages(c: &mut Criterion) {
+    bench_stage("stage indexed", c, indexed_stage);
+    bench_stage("stage axpy per column", c, axpy_stage);
+    bench_stage("stage linear combination", c, kernel_stage);
+}
+
+criterion_group!(benches, stages);
+criterion_main!(benches);
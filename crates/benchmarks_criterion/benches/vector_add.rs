@@ -5,7 +5,7 @@ use nalgebra::*;
 fn bench_add(
     name: &str,
     c: &mut Criterion,
-    f: impl Fn(DVectorView<f64>, DVectorView<f64>, DVectorViewMut<f64>) -> (),
+    f: impl Fn(DVectorView<f64>, DVectorView<f64>, DVectorViewMut<f64>),
 ) {
     static KB: usize = 1024;
     let mut group = c.benchmark_group(name);
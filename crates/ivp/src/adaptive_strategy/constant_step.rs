@@ -2,14 +2,26 @@ use num_traits::Float;
 
 use super::*;
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConstantStep<F>(pub F);
 
-impl<F: Float, E> AdaptiveStrategy<F, E> for ConstantStep<F> {
-    fn init_dt(&self) -> F {
+impl<F: Scalar + Float, E> AdaptiveStrategy<F, E> for ConstantStep<F> {
+    type State = ();
+
+    fn init_dt<S: OdeSystem<F>>(&self, _sys: &S, _y0: DVectorView<F>, _tspan: TSpan<F>) -> F {
         self.0
     }
 
-    fn try_accept(&self, _cur_dt: F, _error: E, _y: DVectorView<F>) -> Result<F, F> {
-        Ok(self.0)
+    fn init_state(&self) {}
+
+    fn try_accept(
+        &self,
+        _state: &mut (),
+        cur_dt: F,
+        _error: &E,
+        _y0: DVectorView<F>,
+        _y1: DVectorView<F>,
+    ) -> Result<F, F> {
+        Ok(cur_dt.signum() * self.0.abs())
     }
 }
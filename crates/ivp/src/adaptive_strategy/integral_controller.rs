@@ -1,35 +1,176 @@
 use nalgebra::*;
 use num_traits::Float;
 
-use crate::adaptive_strategy::AdaptiveStrategy;
+use crate::adaptive_strategy::{AdaptiveStrategy, hairer_init_dt};
+use crate::{OdeSystem, TSpan, Tolerance, weighted_rms_norm};
 
+/// Integral (a.k.a. proportional, or "standard") step size controller
+///
+/// The step is accepted if the error normalized by the tolerance `E` is at most
+/// one, and the next step size is `dt * gamma * (1/E)^(1/(order+1))`, clamped
+/// so that it changes by a factor between `qmin` and `qmax`. Right after a
+/// rejected step, the step size is not allowed to grow.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "F: Scalar + serde::Serialize",
+        deserialize = "F: Scalar + serde::Deserialize<'de>"
+    ))
+)]
 pub struct IntegralController<F> {
-    init: F,
-    atol: F,
-    rtol: F,
+    init: Option<F>,
+    atol: Tolerance<F>,
+    rtol: Tolerance<F>,
     order: u32,
+    gamma: F,
+    qmin: F,
+    qmax: F,
 }
 
-impl<F> IntegralController<F> {
-    pub fn new(init: F, atol: F, rtol: F, order: u32) -> Self {
+pub struct IntegralState {
+    last_rejected: bool,
+}
+
+impl<F: Float> IntegralController<F> {
+    pub fn new(atol: impl Into<Tolerance<F>>, rtol: impl Into<Tolerance<F>>, order: u32) -> Self {
+        let f = |x: f64| F::from(x).unwrap();
         Self {
-            init,
-            atol,
-            rtol,
+            init: None,
+            atol: atol.into(),
+            rtol: rtol.into(),
             order,
+            gamma: f(0.9),
+            qmin: f(0.2),
+            qmax: f(10.),
+        }
+    }
+
+    /// Use a fixed initial step size instead of choosing one automatically
+    pub fn with_init_dt(self, dt: F) -> Self {
+        Self {
+            init: Some(dt),
+            ..self
+        }
+    }
+
+    /// Set the safety factor `gamma` that the proposed step size is multiplied by
+    pub fn with_safety_factor(self, gamma: F) -> Self {
+        Self { gamma, ..self }
+    }
+
+    /// Set the minimum and maximum factor the step size can change by in one step
+    pub fn with_growth_limits(self, qmin: F, qmax: F) -> Self {
+        Self { qmin, qmax, ..self }
+    }
+}
+
+impl<F: Scalar + Float> AdaptiveStrategy<F, DVector<F>> for IntegralController<F> {
+    type State = IntegralState;
+
+    fn init_dt<S: OdeSystem<F>>(&self, sys: &S, y0: DVectorView<F>, tspan: TSpan<F>) -> F {
+        self.init.unwrap_or_else(|| {
+            hairer_init_dt(sys, y0, tspan, &self.atol, &self.rtol, self.order + 1)
+        })
+    }
+
+    fn init_state(&self) -> Self::State {
+        IntegralState {
+            last_rejected: false,
+        }
+    }
+
+    fn try_accept(
+        &self,
+        state: &mut Self::State,
+        cur_dt: F,
+        error: &DVector<F>,
+        y0: DVectorView<F>,
+        y1: DVectorView<F>,
+    ) -> Result<F, F> {
+        let eest = weighted_rms_norm(error.as_view(), y0, y1, &self.atol, &self.rtol);
+        let q = if eest.is_zero() {
+            self.qmax
+        } else {
+            let expo = F::from(self.order + 1).unwrap().recip();
+            (self.gamma * eest.recip().powf(expo))
+                .max(self.qmin)
+                .min(self.qmax)
+        };
+        if eest > F::one() || !eest.is_finite() {
+            state.last_rejected = true;
+            return Err(cur_dt * q.min(F::one()));
         }
+        let q = if state.last_rejected {
+            q.min(F::one())
+        } else {
+            q
+        };
+        state.last_rejected = false;
+        Ok(cur_dt * q)
     }
 }
 
-impl<F: Float + PartialOrd> AdaptiveStrategy<F, F> for IntegralController<F> {
-    fn init_dt(&self) -> F {
-        self.init
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    /// `try_accept` for a normalized error of `eest`, with `atol = 1` and
+    /// `rtol = 0` so that the norm of `[eest]` is `eest`
+    fn try_accept(
+        controller: &IntegralController<f64>,
+        state: &mut IntegralState,
+        dt: f64,
+        eest: f64,
+    ) -> Result<f64, f64> {
+        let y = DVector::zeros(1);
+        controller.try_accept(
+            state,
+            dt,
+            &DVector::from_element(1, eest),
+            y.as_view(),
+            y.as_view(),
+        )
+    }
+
+    #[test]
+    fn accepts_up_to_one() {
+        let controller = IntegralController::new(1., 0., 4);
+        let mut state = controller.init_state();
+        let dt = try_accept(&controller, &mut state, 0.1, 0.5).unwrap();
+        assert_relative_eq!(dt, 0.1 * 0.9 * f64::powf(2., 0.2));
+        let dt = try_accept(&controller, &mut state, 0.1, 1.01).unwrap_err();
+        assert!(0. < dt && dt < 0.1);
+        let dt = try_accept(&controller, &mut state, -0.1, f64::NAN).unwrap_err();
+        assert!(-0.1 < dt && dt < 0.);
+    }
+
+    #[test]
+    fn clamps_the_change() {
+        let controller = IntegralController::new(1., 0., 4).with_growth_limits(0.5, 2.);
+        let mut state = controller.init_state();
+        assert_relative_eq!(try_accept(&controller, &mut state, 0.1, 0.).unwrap(), 0.2);
+        assert_relative_eq!(
+            try_accept(&controller, &mut state, 0.1, 1e-12).unwrap(),
+            0.2
+        );
+        assert_relative_eq!(
+            try_accept(&controller, &mut state, 0.1, 1e10).unwrap_err(),
+            0.05
+        );
     }
 
-    fn try_accept(&self, cur_dt: F, error: F, y: DVectorView<F>) -> Result<F, F> {
-        let tol =
-            self.atol + self.rtol * y.iter().map(|x| x.abs()).reduce(|x, y| x.max(y)).unwrap();
-        let new_dt = cur_dt * (tol / error).powf(F::from(self.order + 1).unwrap().recip());
-        Ok(new_dt)
+    #[test]
+    fn no_growth_after_a_rejection() {
+        let controller = IntegralController::new(1., 0., 4);
+        let mut state = controller.init_state();
+        let dt = try_accept(&controller, &mut state, 0.1, 2.).unwrap_err();
+        assert_eq!(try_accept(&controller, &mut state, dt, 1e-3), Ok(dt));
+        assert!(try_accept(&controller, &mut state, dt, 1e-3).unwrap() > dt);
+        // Shrinking is still allowed
+        try_accept(&controller, &mut state, dt, 2.).unwrap_err();
+        assert!(try_accept(&controller, &mut state, dt, 0.99).unwrap() < dt);
     }
 }
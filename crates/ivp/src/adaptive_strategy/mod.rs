@@ -1,4 +1,7 @@
 use nalgebra::*;
+use num_traits::Float;
+
+use crate::{OdeSystem, TSpan, Tolerance, weighted_rms_norm};
 
 pub mod constant_step;
 pub mod integral_controller;
@@ -8,10 +11,72 @@ pub use constant_step::*;
 pub use integral_controller::*;
 pub use proportional_integral_controller::*;
 
-pub trait AdaptiveStrategy<F, E> {
-    fn init_dt(&self) -> F;
+pub trait AdaptiveStrategy<F: Scalar + Float, E> {
+    /// Whatever the strategy needs to remember between steps, e.g. the error of
+    /// the previous accepted step for a PI controller
+    type State;
+
+    /// The size of the first step. The integrator takes care of the sign when
+    /// integrating backward in time, so only the magnitude matters.
+    fn init_dt<S: OdeSystem<F>>(&self, sys: &S, y0: DVectorView<F>, tspan: TSpan<F>) -> F;
+
+    fn init_state(&self) -> Self::State;
+
+    /// Return Ok(new_dt) if the step from `y0` to `y1` succeeds, and
+    /// Err(new_dt) if the step should be retried with a smaller dt. `new_dt`
+    /// should have the same sign as `cur_dt`.
+    fn try_accept(
+        &self,
+        state: &mut Self::State,
+        cur_dt: F,
+        error: &E,
+        y0: DVectorView<F>,
+        y1: DVectorView<F>,
+    ) -> Result<F, F>;
+}
+
+/// Choose an initial step size for a method of order `order`, following the
+/// algorithm in Hairer, Nørsett, Wanner, "Solving Ordinary Differential
+/// Equations I", section II.4.
+///
+/// This costs two evaluations of the vector field. The result is always
+/// positive, even when `tspan` runs backward.
+pub fn hairer_init_dt<F: Scalar + Float, S: OdeSystem<F>>(
+    sys: &S,
+    y0: DVectorView<F>,
+    tspan: TSpan<F>,
+    atol: &Tolerance<F>,
+    rtol: &Tolerance<F>,
+    order: u32,
+) -> F {
+    let f = |x: f64| F::from(x).unwrap();
+    let n = sys.dimension();
+    let t0 = tspan.start;
+    let tdir = tspan.direction();
+    let max_dt = (tspan.end - t0).abs();
+    let norm = |v: &DVector<F>| weighted_rms_norm(v.as_view(), y0, y0, atol, rtol);
+
+    let mut f0 = DVector::zeros(n);
+    sys.vfield(f0.as_view_mut(), y0, t0);
+    let d0 = norm(&y0.into_owned());
+    let d1 = norm(&f0);
+    let h0 = if d0 < f(1e-5) || d1 < f(1e-5) {
+        f(1e-6)
+    } else {
+        f(0.01) * d0 / d1
+    }
+    .min(max_dt);
+
+    let y1 = y0.zip_map(&f0, |yi, fi| yi + tdir * h0 * fi);
+    let mut f1 = DVector::zeros(n);
+    sys.vfield(f1.as_view_mut(), y1.as_view(), t0 + tdir * h0);
+    let d2 = norm(&f1.zip_map(&f0, |a, b| a - b)) / h0;
 
-    /// Return Ok(new_dt) if the step succeeds, and Err(new_dt) if the step
-    /// should be retried with a smaller dt
-    fn try_accept(&self, cur_dt: F, error: E, y: DVectorView<F>) -> Result<F, F>;
+    let dmax = d1.max(d2);
+    let h1 = if dmax <= f(1e-15) {
+        (h0 * f(1e-3)).max(f(1e-6))
+    } else {
+        (f(0.01) / dmax).powf(F::from(order + 1).unwrap().recip())
+    };
+    (f(100.) * h0).min(h1).min(max_dt)
 }
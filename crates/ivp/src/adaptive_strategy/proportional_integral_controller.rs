@@ -1,41 +1,216 @@
 use nalgebra::*;
 use num_traits::Float;
 
-use crate::adaptive_strategy::AdaptiveStrategy;
+use crate::adaptive_strategy::{AdaptiveStrategy, hairer_init_dt};
+use crate::{OdeSystem, TSpan, Tolerance, weighted_rms_norm};
 
+/// PI step size controller, following
+/// https://github.com/SciML/OrdinaryDiffEq.jl/blob/8d830c9cc6326904d54896971194010c421ee890/lib/OrdinaryDiffEqCore/src/integrators/controllers.jl#L73
+///
+/// The new step size is `dt * gamma / (E^beta1 / E_old^beta2)`, clamped so that
+/// it changes by a factor between `qmin` and `qmax`, where `E` is the error
+/// normalized by the tolerance and `E_old` is the normalized error of the last
+/// accepted step. Right after a rejected step, the step size is not allowed to
+/// grow.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "F: Scalar + serde::Serialize",
+        deserialize = "F: Scalar + serde::Deserialize<'de>"
+    ))
+)]
 pub struct ProportionalIntegralController<F> {
-    init: F,
-    atol: F,
-    rtol: F,
+    init: Option<F>,
+    atol: Tolerance<F>,
+    rtol: Tolerance<F>,
     order: u32,
     beta1: F,
     beta2: F,
+    gamma: F,
+    qmin: F,
+    qmax: F,
+    qold_init: F,
 }
 
-impl<F> ProportionalIntegralController<F> {
-    pub fn new(init: F, atol: F, rtol: F, order: u32) -> Self {
-        todo!()
-        // Self {
-        //     init,
-        //     atol,
-        //     rtol,
-        //     order,
-        // }
+pub struct ProportionalIntegralState<F> {
+    qold: F,
+    last_rejected: bool,
+}
+
+impl<F: Float> ProportionalIntegralController<F> {
+    /// `order` is the order of the error estimate, i.e. the order of the
+    /// lower-order method in an embedded pair
+    pub fn new(atol: impl Into<Tolerance<F>>, rtol: impl Into<Tolerance<F>>, order: u32) -> Self {
+        let f = |x: f64| F::from(x).unwrap();
+        let p = f((order + 1).into());
+        Self {
+            init: None,
+            atol: atol.into(),
+            rtol: rtol.into(),
+            order,
+            beta1: f(0.7) / p,
+            beta2: f(0.4) / p,
+            gamma: f(0.9),
+            qmin: f(0.2),
+            qmax: f(10.),
+            qold_init: f(1e-4),
+        }
+    }
+
+    pub fn with_gains(self, beta1: F, beta2: F) -> Self {
+        Self {
+            beta1,
+            beta2,
+            ..self
+        }
+    }
+
+    /// Use a fixed initial step size instead of choosing one automatically
+    pub fn with_init_dt(self, dt: F) -> Self {
+        Self {
+            init: Some(dt),
+            ..self
+        }
+    }
+
+    /// Set the safety factor `gamma` that the proposed step size is multiplied by
+    pub fn with_safety_factor(self, gamma: F) -> Self {
+        Self { gamma, ..self }
+    }
+
+    /// Set the minimum and maximum factor the step size can change by in one step
+    pub fn with_growth_limits(self, qmin: F, qmax: F) -> Self {
+        Self { qmin, qmax, ..self }
+    }
+}
+
+impl<F: Scalar + Float> AdaptiveStrategy<F, DVector<F>> for ProportionalIntegralController<F> {
+    type State = ProportionalIntegralState<F>;
+
+    fn init_dt<S: OdeSystem<F>>(&self, sys: &S, y0: DVectorView<F>, tspan: TSpan<F>) -> F {
+        self.init.unwrap_or_else(|| {
+            hairer_init_dt(sys, y0, tspan, &self.atol, &self.rtol, self.order + 1)
+        })
+    }
+
+    fn init_state(&self) -> Self::State {
+        ProportionalIntegralState {
+            qold: self.qold_init,
+            last_rejected: false,
+        }
+    }
+
+    fn try_accept(
+        &self,
+        state: &mut Self::State,
+        cur_dt: F,
+        error: &DVector<F>,
+        y0: DVectorView<F>,
+        y1: DVectorView<F>,
+    ) -> Result<F, F> {
+        let eest = weighted_rms_norm(error.as_view(), y0, y1, &self.atol, &self.rtol);
+        if eest > F::one() || !eest.is_finite() {
+            let q11 = eest.powf(self.beta1);
+            state.last_rejected = true;
+            return Err(cur_dt / (q11 / self.gamma).min(self.qmin.recip()));
+        }
+        let q = if eest.is_zero() {
+            self.qmax.recip()
+        } else {
+            let q = eest.powf(self.beta1) / state.qold.powf(self.beta2);
+            (q / self.gamma)
+                .min(self.qmin.recip())
+                .max(self.qmax.recip())
+        };
+        let q = if state.last_rejected {
+            q.max(F::one())
+        } else {
+            q
+        };
+        state.qold = eest.max(self.qold_init);
+        state.last_rejected = false;
+        Ok(cur_dt / q)
     }
 }
 
-impl<F: Float + PartialOrd> AdaptiveStrategy<F, F> for ProportionalIntegralController<F> {
-    fn init_dt(&self) -> F {
-        self.init
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    /// `try_accept` for a normalized error of `eest`, with `atol = 1` and
+    /// `rtol = 0` so that the norm of `[eest]` is `eest`
+    fn try_accept(
+        controller: &ProportionalIntegralController<f64>,
+        state: &mut ProportionalIntegralState<f64>,
+        dt: f64,
+        eest: f64,
+    ) -> Result<f64, f64> {
+        let y = DVector::zeros(1);
+        controller.try_accept(
+            state,
+            dt,
+            &DVector::from_element(1, eest),
+            y.as_view(),
+            y.as_view(),
+        )
+    }
+
+    #[test]
+    fn keeps_the_last_error() {
+        let controller = ProportionalIntegralController::new(1., 0., 4);
+        let mut state = controller.init_state();
+        // OrdinaryDiffEq's PIController, with beta1 = 0.7/5, beta2 = 0.4/5
+        let (beta1, beta2, gamma) = (0.14, 0.08, 0.9);
+        let mut qold = 1e-4;
+        let mut dt = 0.1;
+        for eest in [0.5, 0.01, 0.8, 0.3, 1e-6] {
+            let q = (f64::powf(eest, beta1) / f64::powf(qold, beta2) / gamma).clamp(0.1, 5.);
+            let expected = dt / q;
+            dt = try_accept(&controller, &mut state, dt, eest).unwrap();
+            assert_relative_eq!(dt, expected, max_relative = 1e-14);
+            qold = eest.max(1e-4);
+            assert_relative_eq!(state.qold, qold);
+        }
+    }
+
+    #[test]
+    fn accepts_up_to_one() {
+        let controller = ProportionalIntegralController::new(1., 0., 4);
+        let mut state = controller.init_state();
+        assert!(try_accept(&controller, &mut state, 0.1, 0.99).is_ok());
+        let dt = try_accept(&controller, &mut state, 0.1, 1.01).unwrap_err();
+        assert!(0. < dt && dt < 0.1);
+        let dt = try_accept(&controller, &mut state, -0.1, f64::NAN).unwrap_err();
+        assert!(-0.1 < dt && dt < 0.);
+    }
+
+    #[test]
+    fn clamps_the_change() {
+        let controller = ProportionalIntegralController::new(1., 0., 4).with_growth_limits(0.5, 2.);
+        let mut state = controller.init_state();
+        assert_relative_eq!(try_accept(&controller, &mut state, 0.1, 0.).unwrap(), 0.2);
+        assert_relative_eq!(
+            try_accept(&controller, &mut state, 0.1, 1e-12).unwrap(),
+            0.2
+        );
+        assert_relative_eq!(
+            try_accept(&controller, &mut state, 0.1, 1e10).unwrap_err(),
+            0.05
+        );
     }
 
-    // TODO: implement PI controller, taking code from: https://github.com/SciML/OrdinaryDiffEq.jl/blob/8d830c9cc6326904d54896971194010c421ee890/lib/OrdinaryDiffEqCore/src/integrators/controllers.jl#L73
-    // It seems like it might make sense to factor out init, atol, rtol, etc. to be
-    // common to PI controller and P controller
-    fn try_accept(&self, cur_dt: F, error: F, y: DVectorView<F>) -> Result<F, F> {
-        let tol =
-            self.atol + self.rtol * y.iter().map(|x| x.abs()).reduce(|x, y| x.max(y)).unwrap();
-        let new_dt = cur_dt * (tol / error).powf(F::from(self.order + 1).unwrap().recip());
-        Ok(new_dt)
+    #[test]
+    fn no_growth_after_a_rejection() {
+        let controller = ProportionalIntegralController::new(1., 0., 4);
+        let mut state = controller.init_state();
+        let dt = try_accept(&controller, &mut state, 0.1, 2.).unwrap_err();
+        assert_eq!(try_accept(&controller, &mut state, dt, 1e-3), Ok(dt));
+        assert!(try_accept(&controller, &mut state, dt, 1e-3).unwrap() > dt);
+        // Shrinking is still allowed
+        try_accept(&controller, &mut state, dt, 2.).unwrap_err();
+        assert!(try_accept(&controller, &mut state, dt, 0.99).unwrap() < dt);
     }
 }
@@ -0,0 +1,341 @@
+use nalgebra::*;
+use num_traits::Float;
+
+use super::Event;
+
+type Condition<'a, F> = Box<dyn Fn(DVectorView<F>, F) -> F + 'a>;
+type Affect<'a, F> = Box<dyn FnMut(&mut Event<F>) + 'a>;
+
+/// Which sign changes of the condition count as events
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Crossing {
+    /// From negative to non-negative
+    Rising,
+    /// From positive to non-positive
+    Falling,
+    Either,
+}
+
+/// A callback for the times where a condition `g(y, t)` changes sign, e.g. a
+/// population falling below some level.
+///
+/// After each accepted step the condition is checked on the dense output at
+/// evenly spaced points through the step, see
+/// [`with_interp_points`](Self::with_interp_points). At the first sign change
+/// the time of the event is found by root finding between the two points, and
+/// the step is redone up to there. The state at the event is the end of the
+/// redone step, so the condition there is only zero to within the local error.
+///
+/// Right after an event, or when a step starts on a root, the sign the
+/// condition leaves with is read where it has moved clearly off zero, from a few
+/// ulps of `t` into the next step on, so that the same root isn't found again.
+/// Only events closer to the last one than that are missed.
+pub struct ContinuousCallback<'a, F: Scalar> {
+    condition: Condition<'a, F>,
+    affect: Affect<'a, F>,
+    crossing: Crossing,
+    interp_points: usize,
+    /// The condition at the last accepted step
+    prev: F,
+    /// Whether the last accepted step ended on an event of this callback
+    fired: bool,
+}
+
+impl<'a, F: Float + Scalar> ContinuousCallback<'a, F> {
+    pub fn new(
+        condition: impl Fn(DVectorView<F>, F) -> F + 'a,
+        affect: impl FnMut(&mut Event<F>) + 'a,
+    ) -> Self {
+        Self {
+            condition: Box::new(condition),
+            affect: Box::new(affect),
+            crossing: Crossing::Either,
+            interp_points: 10,
+            prev: F::zero(),
+            fired: false,
+        }
+    }
+
+    /// Only react to the given sign changes, by default either
+    pub fn with_crossing(self, crossing: Crossing) -> Self {
+        Self { crossing, ..self }
+    }
+
+    /// Check the condition at this many evenly spaced points of each step, 10
+    /// by default, to catch an even number of sign changes within a step.
+    /// Events closer together than the spacing of the points can still be
+    /// missed.
+    pub fn with_interp_points(self, interp_points: usize) -> Self {
+        assert!(interp_points > 0, "the end of the step must be checked");
+        Self {
+            interp_points,
+            ..self
+        }
+    }
+
+    /// Start watching the condition from `y` at `t`, where an event was just
+    /// handled if `fired`
+    pub(crate) fn reset(&mut self, y: DVectorView<F>, t: F, fired: bool) {
+        self.prev = (self.condition)(y, t);
+        self.fired = fired;
+    }
+
+    pub(crate) fn affect(&mut self, event: &mut Event<F>) {
+        (self.affect)(event)
+    }
+
+    /// The fraction of the step from `t` to `t + dt` where the first event of
+    /// this callback happens, if any. `y` evaluates the dense output of the
+    /// step, and `y1` is its end.
+    pub(crate) fn find_event(
+        &self,
+        y: impl Fn(F) -> DVector<F>,
+        y1: DVectorView<F>,
+        t: F,
+        dt: F,
+    ) -> Option<F> {
+        let g = |s: F, y: DVectorView<F>| (self.condition)(y, t + s * dt);
+        let crossed = |glo: F, ghi: F| match self.crossing {
+            Crossing::Rising => glo < F::zero() && ghi >= F::zero(),
+            Crossing::Falling => glo > F::zero() && ghi <= F::zero(),
+            Crossing::Either => {
+                (glo < F::zero() && ghi >= F::zero()) || (glo > F::zero() && ghi <= F::zero())
+            }
+        };
+        let n = F::from(self.interp_points).unwrap();
+        // The root finding tolerance, as a fraction of the step
+        let tol = F::from(4.).unwrap() * F::epsilon() * t.abs().max(dt.abs()) / dt.abs();
+        // Right after an event the state is only within the local error of the
+        // root, so the condition may still have the sign it had before it. The
+        // sign it leaves with is read once it is further from zero than twice
+        // where it started, looking from the root finding tolerance on at
+        // doubling distances up to the first point checked.
+        let (mut lo, mut glo) = if self.fired || self.prev == F::zero() {
+            let two = F::from(2.).unwrap();
+            let mut s = tol;
+            loop {
+                let gs = g(s, y(s).as_view());
+                if gs.abs() > two * self.prev.abs() || two * s >= n.recip() {
+                    break (s, gs);
+                }
+                s = two * s;
+            }
+        } else {
+            (F::zero(), self.prev)
+        };
+        for k in 1..=self.interp_points {
+            let (hi, ghi) = if k == self.interp_points {
+                (F::one(), g(F::one(), y1))
+            } else {
+                let s = F::from(k).unwrap() / n;
+                if s <= lo {
+                    continue;
+                }
+                (s, g(s, y(s).as_view()))
+            };
+            if !crossed(glo, ghi) {
+                (lo, glo) = (hi, ghi);
+                continue;
+            }
+            if ghi == F::zero() {
+                return Some(hi);
+            }
+            return Some(find_root(|s| g(s, y(s).as_view()), lo, glo, hi, ghi, tol));
+        }
+        None
+    }
+}
+
+/// Narrow the bracket `[lo, hi]` of a root of `g` with the Illinois method
+/// until it is shorter than `tol`, and return its end where `g` has the sign it
+/// has at `lo`, or a root
+fn find_root<F: Float>(
+    g: impl Fn(F) -> F,
+    mut lo: F,
+    mut glo: F,
+    mut hi: F,
+    mut ghi: F,
+    tol: F,
+) -> F {
+    let two = F::from(2.).unwrap();
+    // Which end moved last, to halve the value kept at the other end when the
+    // same end moves twice in a row
+    let mut last = 0;
+    for _ in 0..100 {
+        if hi - lo <= tol {
+            break;
+        }
+        let mut s = hi - ghi * (hi - lo) / (ghi - glo);
+        if !(lo < s && s < hi) {
+            s = (lo + hi) / two;
+        }
+        let gs = g(s);
+        if gs == F::zero() {
+            return s;
+        }
+        if (gs < F::zero()) == (glo < F::zero()) {
+            lo = s;
+            glo = gs;
+            if last == -1 {
+                ghi = ghi / two;
+            }
+            last = -1;
+        } else {
+            hi = s;
+            ghi = gs;
+            if last == 1 {
+                glo = glo / two;
+            }
+            last = 1;
+        }
+    }
+    lo
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use approx::assert_relative_eq;
+
+    use super::*;
+    use crate::{
+        ConstantStep, IntegralController, OdeProblem, OdeSystem, SolveOptions, TSpan,
+        linear_solver::tests::Linear, step_algorithm::DP5,
+    };
+
+    /// `y' = -1`, which every step algorithm integrates exactly
+    struct Drift;
+
+    impl OdeSystem<f64> for Drift {
+        fn dimension(&self) -> usize {
+            1
+        }
+
+        fn labels(&self) -> Vec<String> {
+            vec!["y".to_string()]
+        }
+
+        fn vfield(&self, mut out: DVectorViewMut<f64>, _y: DVectorView<f64>, _t: f64) {
+            out[0] = -1.;
+        }
+    }
+
+    /// `y'' = -y` as a first order system, with solution `(cos t, -sin t)`
+    fn oscillator() -> OdeProblem<f64, Linear> {
+        let sys = Linear(DMatrix::from_row_slice(2, 2, &[0., 1., -1., 0.]));
+        OdeProblem::new(sys, dvector![1., 0.], TSpan::new(0., 10.))
+    }
+
+    fn controller() -> IntegralController<f64> {
+        IntegralController::new(1e-10, 1e-10, 4)
+    }
+
+    /// The times of the events of `condition` over the whole time span
+    fn event_times<S: OdeSystem<f64>>(
+        problem: &OdeProblem<f64, S>,
+        condition: impl Fn(DVectorView<f64>, f64) -> f64,
+        crossing: Crossing,
+    ) -> Vec<f64> {
+        let mut times = vec![];
+        let callback = ContinuousCallback::new(condition, |event| times.push(event.t()))
+            .with_crossing(crossing);
+        problem
+            .init(&DP5, &controller(), SolveOptions::default())
+            .with_callback(callback)
+            .solve()
+            .unwrap();
+        times
+    }
+
+    #[test]
+    fn analytic_crossing() {
+        let problem = OdeProblem::new(Drift, dvector![1.], TSpan::new(0., 2.));
+        let times = event_times(&problem, |y, _| y[0] - 0.5, Crossing::Either);
+        assert_eq!(times.len(), 1);
+        assert_relative_eq!(times[0], 0.5, max_relative = 1e-14);
+    }
+
+    #[test]
+    fn crossing_directions() {
+        // -sin t rises through zero at π and 3π, and falls at 2π
+        let problem = oscillator();
+        let condition = |y: DVectorView<f64>, _| y[1];
+        for (crossing, expected) in [
+            (Crossing::Rising, vec![PI, 3. * PI]),
+            (Crossing::Falling, vec![2. * PI]),
+            (Crossing::Either, vec![PI, 2. * PI, 3. * PI]),
+        ] {
+            let times = event_times(&problem, condition, crossing);
+            assert_eq!(times.len(), expected.len(), "{crossing:?}: {times:?}");
+            for (t, expected) in times.into_iter().zip(expected) {
+                assert_relative_eq!(t, expected, max_relative = 1e-8);
+            }
+        }
+    }
+
+    #[test]
+    fn close_events() {
+        // The roots are apart by more than the spacing of the points checked,
+        // but by less than a hundredth of a step
+        let problem = OdeProblem::new(Drift, dvector![1.], TSpan::new(0., 2.));
+        let mut times = vec![];
+        let callback = ContinuousCallback::new(
+            |_, t| (t - 1.1003) * (t - 1.1023),
+            |event| times.push(event.t()),
+        )
+        .with_interp_points(200);
+        problem
+            .init(&DP5, &ConstantStep(0.25), SolveOptions::default())
+            .with_callback(callback)
+            .solve()
+            .unwrap();
+        assert_eq!(times.len(), 2, "{times:?}");
+        assert_relative_eq!(times[0], 1.1003, max_relative = 1e-14);
+        assert_relative_eq!(times[1], 1.1023, max_relative = 1e-14);
+    }
+
+    #[test]
+    fn terminate_stops_at_the_event() {
+        let problem = OdeProblem::new(Drift, dvector![1.], TSpan::new(0., 2.));
+        let callback = ContinuousCallback::new(|y, _| y[0] - 0.5, |event| event.terminate());
+        let controller = controller();
+        let solution = problem
+            .init(&DP5, &controller, SolveOptions::default())
+            .with_callback(callback)
+            .solve()
+            .unwrap();
+        assert_relative_eq!(*solution.ts().last().unwrap(), 0.5, max_relative = 1e-14);
+        assert_relative_eq!(solution.ys().last().unwrap()[0], 0.5, max_relative = 1e-14);
+    }
+
+    #[test]
+    fn redo_to_the_event() {
+        // cos t falls through 0.5 at π/3, which the steps don't land on
+        let problem = oscillator();
+        let mut events = vec![];
+        let callback = ContinuousCallback::new(
+            |y, _| y[0] - 0.5,
+            |event| events.push((event.t(), event.y().clone_owned())),
+        )
+        .with_crossing(Crossing::Falling);
+        let controller = controller();
+        let solution = problem
+            .init(&DP5, &controller, SolveOptions::default())
+            .with_callback(callback)
+            .solve()
+            .unwrap();
+        let (t, y) = &events[0];
+        assert_relative_eq!(*t, PI / 3., max_relative = 1e-8);
+        assert_relative_eq!(*y, dvector![0.5, -f64::sin(*t)], epsilon = 1e-8);
+        // The step that ends on the event is the redone one, and its dense
+        // output and the steps after it carry on from there
+        let i = solution.ts().iter().position(|ts| ts == t).unwrap();
+        assert_eq!(&solution.ys()[i], y);
+        let (t0, t1) = (solution.ts()[i - 1], solution.ts()[i + 1]);
+        for t in [(t0 + t) / 2., (t + t1) / 2., 10.] {
+            let expected = dvector![f64::cos(t), -f64::sin(t)];
+            assert_relative_eq!(solution.solution_at(t), expected, epsilon = 1e-8);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use nalgebra::*;
+
+use super::Event;
+
+type Condition<'a, F> = Box<dyn Fn(DVectorView<F>, F) -> bool + 'a>;
+type Affect<'a, F> = Box<dyn FnMut(&mut Event<F>) + 'a>;
+
+/// A callback checked after every accepted step, whose affect runs whenever
+/// `condition(y, t)` holds at the end of the step, e.g. for a dose given at
+/// fixed times or to log the state.
+///
+/// Unlike [`ContinuousCallback`](super::ContinuousCallback) it doesn't shorten
+/// steps, so for something to happen at a given time that time should also be
+/// a tstop.
+pub struct DiscreteCallback<'a, F: Scalar> {
+    condition: Condition<'a, F>,
+    affect: Affect<'a, F>,
+}
+
+impl<'a, F: Scalar> DiscreteCallback<'a, F> {
+    pub fn new(
+        condition: impl Fn(DVectorView<F>, F) -> bool + 'a,
+        affect: impl FnMut(&mut Event<F>) + 'a,
+    ) -> Self {
+        Self {
+            condition: Box::new(condition),
+            affect: Box::new(affect),
+        }
+    }
+
+    /// A callback whose affect runs after every accepted step
+    pub fn every_step(affect: impl FnMut(&mut Event<F>) + 'a) -> Self {
+        Self::new(|_, _| true, affect)
+    }
+
+    pub(crate) fn condition(&self, y: DVectorView<F>, t: F) -> bool {
+        (self.condition)(y, t)
+    }
+
+    pub(crate) fn affect(&mut self, event: &mut Event<F>) {
+        (self.affect)(event)
+    }
+}
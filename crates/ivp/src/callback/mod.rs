@@ -0,0 +1,75 @@
+use nalgebra::*;
+
+pub mod continuous;
+pub mod discrete;
+
+pub use continuous::*;
+pub use discrete::*;
+
+/// Something to watch for while integrating, see [`Integrator::with_callback`]
+///
+/// [`Integrator::with_callback`]: crate::Integrator::with_callback
+pub enum Callback<'a, F: Scalar> {
+    Continuous(ContinuousCallback<'a, F>),
+    Discrete(DiscreteCallback<'a, F>),
+}
+
+impl<'a, F: Scalar> From<ContinuousCallback<'a, F>> for Callback<'a, F> {
+    fn from(callback: ContinuousCallback<'a, F>) -> Self {
+        Callback::Continuous(callback)
+    }
+}
+
+impl<'a, F: Scalar> From<DiscreteCallback<'a, F>> for Callback<'a, F> {
+    fn from(callback: DiscreteCallback<'a, F>) -> Self {
+        Callback::Discrete(callback)
+    }
+}
+
+/// The state of the integrator at an event, handed to the affect function of a
+/// callback
+pub struct Event<F: Scalar> {
+    pub(crate) t: F,
+    pub(crate) y: DVector<F>,
+    pub(crate) modified: bool,
+    pub(crate) dt: Option<F>,
+    pub(crate) terminated: bool,
+}
+
+impl<F: Scalar + Copy> Event<F> {
+    pub(crate) fn new(t: F, y: DVector<F>) -> Self {
+        Self {
+            t,
+            y,
+            modified: false,
+            dt: None,
+            terminated: false,
+        }
+    }
+
+    pub fn t(&self) -> F {
+        self.t
+    }
+
+    pub fn y(&self) -> DVectorView<'_, F> {
+        self.y.as_view()
+    }
+
+    /// The state, to change it. The integrator restarts from the changed state,
+    /// and the solution keeps both the states before and after the event.
+    pub fn y_mut(&mut self) -> &mut DVector<F> {
+        self.modified = true;
+        &mut self.y
+    }
+
+    /// Set the size of the next step that is tried, see
+    /// [`Integrator::set_dt`](crate::Integrator::set_dt)
+    pub fn set_dt(&mut self, dt: F) {
+        self.dt = Some(dt);
+    }
+
+    /// End the solve at this event
+    pub fn terminate(&mut self) {
+        self.terminated = true;
+    }
+}
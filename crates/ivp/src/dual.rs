@@ -0,0 +1,984 @@
+use std::cmp::Ordering;
+use std::fmt::{self, Display};
+use std::num::FpCategory;
+use std::ops::*;
+
+use approx::{AbsDiffEq, RelativeEq, UlpsEq};
+use nalgebra::{ComplexField, Field, RealField, SimdValue};
+use num_traits::*;
+use simba::scalar::SubsetOf;
+
+/// A dual number `re + eps ε` with `ε² = 0`, for forward mode automatic
+/// differentiation.
+///
+/// Evaluating a function on `Dual::variable(x)` gives the value of the
+/// function at `x` in `re` and its derivative in `eps`. Comparisons only look
+/// at `re`, so that code branching on values takes the same branch as it
+/// would with plain floats.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Dual<F> {
+    pub re: F,
+    pub eps: F,
+}
+
+impl<F> Dual<F> {
+    pub fn new(re: F, eps: F) -> Self {
+        Self { re, eps }
+    }
+}
+
+impl<F: Zero> Dual<F> {
+    /// A value that doesn't depend on the variable being differentiated
+    pub fn constant(re: F) -> Self {
+        Self::new(re, F::zero())
+    }
+}
+
+impl<F: Zero + One> Dual<F> {
+    /// The variable being differentiated with respect to
+    pub fn variable(re: F) -> Self {
+        Self::new(re, F::one())
+    }
+}
+
+impl<F: Float> Dual<F> {
+    /// The result of applying a function with value `f` and derivative `df`
+    /// at `self.re`
+    fn chain(self, f: F, df: F) -> Self {
+        Self::new(f, df * self.eps)
+    }
+}
+
+impl<F: Zero> From<F> for Dual<F> {
+    fn from(re: F) -> Self {
+        Self::constant(re)
+    }
+}
+
+impl<F: PartialEq> PartialEq for Dual<F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.re == other.re
+    }
+}
+
+impl<F: PartialOrd> PartialOrd for Dual<F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.re.partial_cmp(&other.re)
+    }
+}
+
+impl<F: Display> Display for Dual<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} + {}ε", self.re, self.eps)
+    }
+}
+
+impl<F: Float> Neg for Dual<F> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.re, -self.eps)
+    }
+}
+
+impl<F: Float> Add for Dual<F> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re + rhs.re, self.eps + rhs.eps)
+    }
+}
+
+impl<F: Float> Sub for Dual<F> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.re - rhs.re, self.eps - rhs.eps)
+    }
+}
+
+impl<F: Float> Mul for Dual<F> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(self.re * rhs.re, self.eps * rhs.re + self.re * rhs.eps)
+    }
+}
+
+impl<F: Float> Div for Dual<F> {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        let re = self.re / rhs.re;
+        Self::new(re, (self.eps - re * rhs.eps) / rhs.re)
+    }
+}
+
+impl<F: Float> Rem for Dual<F> {
+    type Output = Self;
+
+    fn rem(self, rhs: Self) -> Self {
+        let re = self.re % rhs.re;
+        let q = ((self.re - re) / rhs.re).round();
+        Self::new(re, self.eps - q * rhs.eps)
+    }
+}
+
+macro_rules! impl_assign_op {
+    ($($trait:ident, $method:ident, $op:tt);*) => {$(
+        impl<F: Float> $trait for Dual<F> {
+            fn $method(&mut self, rhs: Self) {
+                *self = *self $op rhs;
+            }
+        }
+    )*};
+}
+
+impl_assign_op!(
+    AddAssign, add_assign, +;
+    SubAssign, sub_assign, -;
+    MulAssign, mul_assign, *;
+    DivAssign, div_assign, /;
+    RemAssign, rem_assign, %
+);
+
+impl<F: Float> Zero for Dual<F> {
+    fn zero() -> Self {
+        Self::constant(F::zero())
+    }
+
+    fn is_zero(&self) -> bool {
+        self.re.is_zero()
+    }
+}
+
+impl<F: Float> One for Dual<F> {
+    fn one() -> Self {
+        Self::constant(F::one())
+    }
+}
+
+impl<F: Float> Num for Dual<F> {
+    type FromStrRadixErr = F::FromStrRadixErr;
+
+    fn from_str_radix(s: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        F::from_str_radix(s, radix).map(Self::constant)
+    }
+}
+
+impl<F: Float> ToPrimitive for Dual<F> {
+    fn to_i64(&self) -> Option<i64> {
+        self.re.to_i64()
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        self.re.to_u64()
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        self.re.to_f64()
+    }
+}
+
+impl<F: Float> NumCast for Dual<F> {
+    fn from<T: ToPrimitive>(n: T) -> Option<Self> {
+        <F as NumCast>::from(n).map(Self::constant)
+    }
+}
+
+impl<F: Float> FromPrimitive for Dual<F> {
+    fn from_i64(n: i64) -> Option<Self> {
+        <F as NumCast>::from(n).map(Self::constant)
+    }
+
+    fn from_u64(n: u64) -> Option<Self> {
+        <F as NumCast>::from(n).map(Self::constant)
+    }
+
+    fn from_f64(n: f64) -> Option<Self> {
+        <F as NumCast>::from(n).map(Self::constant)
+    }
+}
+
+impl<F: Float> Signed for Dual<F> {
+    fn abs(&self) -> Self {
+        Float::abs(*self)
+    }
+
+    fn abs_sub(&self, other: &Self) -> Self {
+        Float::abs_sub(*self, *other)
+    }
+
+    fn signum(&self) -> Self {
+        Float::signum(*self)
+    }
+
+    fn is_positive(&self) -> bool {
+        self.re > F::zero()
+    }
+
+    fn is_negative(&self) -> bool {
+        self.re < F::zero()
+    }
+}
+
+impl<F: Float> Float for Dual<F> {
+    fn nan() -> Self {
+        Self::constant(F::nan())
+    }
+
+    fn infinity() -> Self {
+        Self::constant(F::infinity())
+    }
+
+    fn neg_infinity() -> Self {
+        Self::constant(F::neg_infinity())
+    }
+
+    fn neg_zero() -> Self {
+        Self::constant(F::neg_zero())
+    }
+
+    fn min_value() -> Self {
+        Self::constant(F::min_value())
+    }
+
+    fn min_positive_value() -> Self {
+        Self::constant(F::min_positive_value())
+    }
+
+    fn epsilon() -> Self {
+        Self::constant(F::epsilon())
+    }
+
+    fn max_value() -> Self {
+        Self::constant(F::max_value())
+    }
+
+    fn is_nan(self) -> bool {
+        self.re.is_nan() || self.eps.is_nan()
+    }
+
+    fn is_infinite(self) -> bool {
+        self.re.is_infinite() || self.eps.is_infinite()
+    }
+
+    fn is_finite(self) -> bool {
+        self.re.is_finite() && self.eps.is_finite()
+    }
+
+    fn is_normal(self) -> bool {
+        self.re.is_normal()
+    }
+
+    fn classify(self) -> FpCategory {
+        self.re.classify()
+    }
+
+    fn floor(self) -> Self {
+        Self::constant(self.re.floor())
+    }
+
+    fn ceil(self) -> Self {
+        Self::constant(self.re.ceil())
+    }
+
+    fn round(self) -> Self {
+        Self::constant(self.re.round())
+    }
+
+    fn trunc(self) -> Self {
+        Self::constant(self.re.trunc())
+    }
+
+    fn fract(self) -> Self {
+        Self::new(self.re.fract(), self.eps)
+    }
+
+    fn abs(self) -> Self {
+        if self.re.is_sign_negative() {
+            -self
+        } else {
+            self
+        }
+    }
+
+    fn signum(self) -> Self {
+        Self::constant(self.re.signum())
+    }
+
+    fn is_sign_positive(self) -> bool {
+        self.re.is_sign_positive()
+    }
+
+    fn is_sign_negative(self) -> bool {
+        self.re.is_sign_negative()
+    }
+
+    fn mul_add(self, a: Self, b: Self) -> Self {
+        Self::new(
+            self.re.mul_add(a.re, b.re),
+            self.eps * a.re + self.re * a.eps + b.eps,
+        )
+    }
+
+    fn recip(self) -> Self {
+        let r = self.re.recip();
+        self.chain(r, -r * r)
+    }
+
+    fn powi(self, n: i32) -> Self {
+        if n == 0 {
+            return Self::one();
+        }
+        let x = self.re.powi(n - 1);
+        self.chain(x * self.re, F::from(n).unwrap() * x)
+    }
+
+    fn powf(self, n: Self) -> Self {
+        let re = self.re.powf(n.re);
+        let mut eps = F::zero();
+        if !self.eps.is_zero() {
+            eps = eps + n.re * self.re.powf(n.re - F::one()) * self.eps;
+        }
+        // Skip the log when the exponent is constant, which would give NaN for
+        // a non-positive base
+        if !n.eps.is_zero() {
+            eps = eps + re * self.re.ln() * n.eps;
+        }
+        Self::new(re, eps)
+    }
+
+    fn sqrt(self) -> Self {
+        let s = self.re.sqrt();
+        self.chain(s, (s + s).recip())
+    }
+
+    fn exp(self) -> Self {
+        let e = self.re.exp();
+        self.chain(e, e)
+    }
+
+    fn exp2(self) -> Self {
+        let e = self.re.exp2();
+        self.chain(e, e * F::from(std::f64::consts::LN_2).unwrap())
+    }
+
+    fn ln(self) -> Self {
+        self.chain(self.re.ln(), self.re.recip())
+    }
+
+    fn log(self, base: Self) -> Self {
+        self.ln() / base.ln()
+    }
+
+    fn log2(self) -> Self {
+        let ln_2 = F::from(std::f64::consts::LN_2).unwrap();
+        self.chain(self.re.log2(), (self.re * ln_2).recip())
+    }
+
+    fn log10(self) -> Self {
+        let ln_10 = F::from(std::f64::consts::LN_10).unwrap();
+        self.chain(self.re.log10(), (self.re * ln_10).recip())
+    }
+
+    fn to_degrees(self) -> Self {
+        Self::new(self.re.to_degrees(), self.eps.to_degrees())
+    }
+
+    fn to_radians(self) -> Self {
+        Self::new(self.re.to_radians(), self.eps.to_radians())
+    }
+
+    fn max(self, other: Self) -> Self {
+        if other.re > self.re || self.re.is_nan() {
+            other
+        } else {
+            self
+        }
+    }
+
+    fn min(self, other: Self) -> Self {
+        if other.re < self.re || self.re.is_nan() {
+            other
+        } else {
+            self
+        }
+    }
+
+    fn abs_sub(self, other: Self) -> Self {
+        if self.re > other.re {
+            self - other
+        } else {
+            Self::zero()
+        }
+    }
+
+    fn cbrt(self) -> Self {
+        let c = self.re.cbrt();
+        self.chain(c, (F::from(3.).unwrap() * c * c).recip())
+    }
+
+    fn hypot(self, other: Self) -> Self {
+        let h = self.re.hypot(other.re);
+        if h.is_zero() {
+            return Self::constant(h);
+        }
+        Self::new(h, (self.re * self.eps + other.re * other.eps) / h)
+    }
+
+    fn sin(self) -> Self {
+        let (s, c) = self.re.sin_cos();
+        self.chain(s, c)
+    }
+
+    fn cos(self) -> Self {
+        let (s, c) = self.re.sin_cos();
+        self.chain(c, -s)
+    }
+
+    fn tan(self) -> Self {
+        let t = self.re.tan();
+        self.chain(t, F::one() + t * t)
+    }
+
+    fn asin(self) -> Self {
+        let d = (F::one() - self.re * self.re).sqrt().recip();
+        self.chain(self.re.asin(), d)
+    }
+
+    fn acos(self) -> Self {
+        let d = -(F::one() - self.re * self.re).sqrt().recip();
+        self.chain(self.re.acos(), d)
+    }
+
+    fn atan(self) -> Self {
+        let d = (F::one() + self.re * self.re).recip();
+        self.chain(self.re.atan(), d)
+    }
+
+    fn atan2(self, other: Self) -> Self {
+        let r2 = self.re * self.re + other.re * other.re;
+        Self::new(
+            self.re.atan2(other.re),
+            (other.re * self.eps - self.re * other.eps) / r2,
+        )
+    }
+
+    fn sin_cos(self) -> (Self, Self) {
+        let (s, c) = self.re.sin_cos();
+        (self.chain(s, c), self.chain(c, -s))
+    }
+
+    fn exp_m1(self) -> Self {
+        self.chain(self.re.exp_m1(), self.re.exp())
+    }
+
+    fn ln_1p(self) -> Self {
+        self.chain(self.re.ln_1p(), (F::one() + self.re).recip())
+    }
+
+    fn sinh(self) -> Self {
+        self.chain(self.re.sinh(), self.re.cosh())
+    }
+
+    fn cosh(self) -> Self {
+        self.chain(self.re.cosh(), self.re.sinh())
+    }
+
+    fn tanh(self) -> Self {
+        let t = self.re.tanh();
+        self.chain(t, F::one() - t * t)
+    }
+
+    fn asinh(self) -> Self {
+        let d = (self.re * self.re + F::one()).sqrt().recip();
+        self.chain(self.re.asinh(), d)
+    }
+
+    fn acosh(self) -> Self {
+        let d = (self.re * self.re - F::one()).sqrt().recip();
+        self.chain(self.re.acosh(), d)
+    }
+
+    fn atanh(self) -> Self {
+        let d = (F::one() - self.re * self.re).recip();
+        self.chain(self.re.atanh(), d)
+    }
+
+    fn integer_decode(self) -> (u64, i16, i8) {
+        self.re.integer_decode()
+    }
+}
+
+// The traits below are what nalgebra needs to treat `Dual` as a field, e.g. to
+// factorize a matrix of duals
+
+impl<F: Float> SimdValue for Dual<F> {
+    const LANES: usize = 1;
+    type Element = Self;
+    type SimdBool = bool;
+
+    fn splat(val: Self) -> Self {
+        val
+    }
+
+    fn extract(&self, _: usize) -> Self {
+        *self
+    }
+
+    unsafe fn extract_unchecked(&self, _: usize) -> Self {
+        *self
+    }
+
+    fn replace(&mut self, _: usize, val: Self) {
+        *self = val
+    }
+
+    unsafe fn replace_unchecked(&mut self, _: usize, val: Self) {
+        *self = val
+    }
+
+    fn select(self, cond: bool, other: Self) -> Self {
+        if cond { self } else { other }
+    }
+}
+
+impl<F: Float> Field for Dual<F> {}
+
+impl<F: Clone> SubsetOf<Dual<F>> for Dual<F> {
+    fn to_superset(&self) -> Self {
+        self.clone()
+    }
+
+    fn from_superset_unchecked(element: &Self) -> Self {
+        element.clone()
+    }
+
+    fn is_in_subset(_: &Self) -> bool {
+        true
+    }
+}
+
+macro_rules! impl_subset_of_dual {
+    ($($t:ty),*) => {$(
+        impl<F: Float> SubsetOf<Dual<F>> for $t {
+            fn to_superset(&self) -> Dual<F> {
+                Dual::constant(F::from(*self).unwrap())
+            }
+
+            fn from_superset_unchecked(element: &Dual<F>) -> Self {
+                <$t as NumCast>::from(element.re).unwrap()
+            }
+
+            fn is_in_subset(element: &Dual<F>) -> bool {
+                element.eps.is_zero()
+            }
+        }
+    )*};
+}
+
+impl_subset_of_dual!(f32, f64);
+
+impl<F: Float + AbsDiffEq<Epsilon = F>> AbsDiffEq for Dual<F> {
+    type Epsilon = Self;
+
+    fn default_epsilon() -> Self {
+        Self::constant(F::default_epsilon())
+    }
+
+    fn abs_diff_eq(&self, other: &Self, epsilon: Self) -> bool {
+        self.re.abs_diff_eq(&other.re, epsilon.re)
+    }
+}
+
+impl<F: Float + RelativeEq<Epsilon = F>> RelativeEq for Dual<F> {
+    fn default_max_relative() -> Self {
+        Self::constant(F::default_max_relative())
+    }
+
+    fn relative_eq(&self, other: &Self, epsilon: Self, max_relative: Self) -> bool {
+        self.re.relative_eq(&other.re, epsilon.re, max_relative.re)
+    }
+}
+
+impl<F: Float + UlpsEq<Epsilon = F>> UlpsEq for Dual<F> {
+    fn default_max_ulps() -> u32 {
+        F::default_max_ulps()
+    }
+
+    fn ulps_eq(&self, other: &Self, epsilon: Self, max_ulps: u32) -> bool {
+        self.re.ulps_eq(&other.re, epsilon.re, max_ulps)
+    }
+}
+
+impl<F: Float + RealField> ComplexField for Dual<F> {
+    type RealField = Self;
+
+    fn from_real(re: Self) -> Self {
+        re
+    }
+
+    fn real(self) -> Self {
+        self
+    }
+
+    fn imaginary(self) -> Self {
+        Self::zero()
+    }
+
+    fn modulus(self) -> Self {
+        Float::abs(self)
+    }
+
+    fn modulus_squared(self) -> Self {
+        self * self
+    }
+
+    fn argument(self) -> Self {
+        if self.re >= F::zero() {
+            Self::zero()
+        } else {
+            Self::constant(F::pi())
+        }
+    }
+
+    fn norm1(self) -> Self {
+        Float::abs(self)
+    }
+
+    fn scale(self, factor: Self) -> Self {
+        self * factor
+    }
+
+    fn unscale(self, factor: Self) -> Self {
+        self / factor
+    }
+
+    fn floor(self) -> Self {
+        Float::floor(self)
+    }
+
+    fn ceil(self) -> Self {
+        Float::ceil(self)
+    }
+
+    fn round(self) -> Self {
+        Float::round(self)
+    }
+
+    fn trunc(self) -> Self {
+        Float::trunc(self)
+    }
+
+    fn fract(self) -> Self {
+        Float::fract(self)
+    }
+
+    fn mul_add(self, a: Self, b: Self) -> Self {
+        Float::mul_add(self, a, b)
+    }
+
+    fn abs(self) -> Self {
+        Float::abs(self)
+    }
+
+    fn hypot(self, other: Self) -> Self {
+        Float::hypot(self, other)
+    }
+
+    fn recip(self) -> Self {
+        Float::recip(self)
+    }
+
+    fn conjugate(self) -> Self {
+        self
+    }
+
+    fn sin(self) -> Self {
+        Float::sin(self)
+    }
+
+    fn cos(self) -> Self {
+        Float::cos(self)
+    }
+
+    fn sin_cos(self) -> (Self, Self) {
+        Float::sin_cos(self)
+    }
+
+    fn tan(self) -> Self {
+        Float::tan(self)
+    }
+
+    fn asin(self) -> Self {
+        Float::asin(self)
+    }
+
+    fn acos(self) -> Self {
+        Float::acos(self)
+    }
+
+    fn atan(self) -> Self {
+        Float::atan(self)
+    }
+
+    fn sinh(self) -> Self {
+        Float::sinh(self)
+    }
+
+    fn cosh(self) -> Self {
+        Float::cosh(self)
+    }
+
+    fn tanh(self) -> Self {
+        Float::tanh(self)
+    }
+
+    fn asinh(self) -> Self {
+        Float::asinh(self)
+    }
+
+    fn acosh(self) -> Self {
+        Float::acosh(self)
+    }
+
+    fn atanh(self) -> Self {
+        Float::atanh(self)
+    }
+
+    fn log(self, base: Self) -> Self {
+        Float::log(self, base)
+    }
+
+    fn log2(self) -> Self {
+        Float::log2(self)
+    }
+
+    fn log10(self) -> Self {
+        Float::log10(self)
+    }
+
+    fn ln(self) -> Self {
+        Float::ln(self)
+    }
+
+    fn ln_1p(self) -> Self {
+        Float::ln_1p(self)
+    }
+
+    fn sqrt(self) -> Self {
+        Float::sqrt(self)
+    }
+
+    fn exp(self) -> Self {
+        Float::exp(self)
+    }
+
+    fn exp2(self) -> Self {
+        Float::exp2(self)
+    }
+
+    fn exp_m1(self) -> Self {
+        Float::exp_m1(self)
+    }
+
+    fn powi(self, n: i32) -> Self {
+        Float::powi(self, n)
+    }
+
+    fn powf(self, n: Self) -> Self {
+        Float::powf(self, n)
+    }
+
+    fn powc(self, n: Self) -> Self {
+        Float::powf(self, n)
+    }
+
+    fn cbrt(self) -> Self {
+        Float::cbrt(self)
+    }
+
+    fn is_finite(&self) -> bool {
+        Float::is_finite(*self)
+    }
+
+    fn try_sqrt(self) -> Option<Self> {
+        (self.re >= F::zero()).then(|| Float::sqrt(self))
+    }
+}
+
+impl<F: Float + RealField> RealField for Dual<F> {
+    fn is_sign_positive(&self) -> bool {
+        Float::is_sign_positive(*self)
+    }
+
+    fn is_sign_negative(&self) -> bool {
+        Float::is_sign_negative(*self)
+    }
+
+    fn copysign(self, sign: Self) -> Self {
+        if Float::is_sign_negative(self.re) == Float::is_sign_negative(sign.re) {
+            self
+        } else {
+            -self
+        }
+    }
+
+    fn max(self, other: Self) -> Self {
+        Float::max(self, other)
+    }
+
+    fn min(self, other: Self) -> Self {
+        Float::min(self, other)
+    }
+
+    fn clamp(self, min: Self, max: Self) -> Self {
+        Float::min(Float::max(self, min), max)
+    }
+
+    fn atan2(self, other: Self) -> Self {
+        Float::atan2(self, other)
+    }
+
+    fn min_value() -> Option<Self> {
+        Some(Float::min_value())
+    }
+
+    fn max_value() -> Option<Self> {
+        Some(Float::max_value())
+    }
+
+    fn pi() -> Self {
+        Self::constant(F::pi())
+    }
+
+    fn two_pi() -> Self {
+        Self::constant(F::two_pi())
+    }
+
+    fn frac_pi_2() -> Self {
+        Self::constant(F::frac_pi_2())
+    }
+
+    fn frac_pi_3() -> Self {
+        Self::constant(F::frac_pi_3())
+    }
+
+    fn frac_pi_4() -> Self {
+        Self::constant(F::frac_pi_4())
+    }
+
+    fn frac_pi_6() -> Self {
+        Self::constant(F::frac_pi_6())
+    }
+
+    fn frac_pi_8() -> Self {
+        Self::constant(F::frac_pi_8())
+    }
+
+    fn frac_1_pi() -> Self {
+        Self::constant(F::frac_1_pi())
+    }
+
+    fn frac_2_pi() -> Self {
+        Self::constant(F::frac_2_pi())
+    }
+
+    fn frac_2_sqrt_pi() -> Self {
+        Self::constant(F::frac_2_sqrt_pi())
+    }
+
+    fn e() -> Self {
+        Self::constant(F::e())
+    }
+
+    fn log2_e() -> Self {
+        Self::constant(F::log2_e())
+    }
+
+    fn log10_e() -> Self {
+        Self::constant(F::log10_e())
+    }
+
+    fn ln_2() -> Self {
+        Self::constant(F::ln_2())
+    }
+
+    fn ln_10() -> Self {
+        Self::constant(F::ln_10())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+
+    /// The derivative of `f` at `x`
+    fn derivative(f: impl Fn(Dual<f64>) -> Dual<f64>, x: f64) -> f64 {
+        f(Dual::variable(x)).eps
+    }
+
+    #[test]
+    fn powf_constant_exponent() {
+        let cube = |x| Float::powf(x, Dual::constant(3.));
+        assert_relative_eq!(derivative(cube, 1.5), 3. * 1.5 * 1.5);
+        // The log of a negative base must not get into the derivative
+        assert_relative_eq!(derivative(cube, -2.), 12.);
+        assert_relative_eq!(
+            derivative(|x| Float::powf(x, Dual::constant(2.5)), 1.5),
+            2.5 * f64::powf(1.5, 1.5)
+        );
+    }
+
+    #[test]
+    fn powf_variable_exponent() {
+        let two = Dual::constant(2.);
+        assert_relative_eq!(
+            derivative(|n| Float::powf(two, n), 3.),
+            8. * f64::ln(2.),
+            max_relative = 1e-15
+        );
+        assert_relative_eq!(
+            derivative(|x| Float::powf(x, x), 1.7),
+            f64::powf(1.7, 1.7) * (f64::ln(1.7) + 1.),
+            max_relative = 1e-15
+        );
+    }
+
+    #[test]
+    fn rem() {
+        // x % y = x - trunc(x / y) y
+        let r = Dual::variable(7.5) % Dual::constant(2.);
+        assert_eq!((r.re, r.eps), (1.5, 1.));
+        let r = Dual::constant(7.5) % Dual::variable(2.);
+        assert_eq!((r.re, r.eps), (1.5, -3.));
+        let r = Dual::constant(-7.5) % Dual::variable(2.);
+        assert_eq!((r.re, r.eps), (-1.5, 3.));
+    }
+
+    #[test]
+    fn atan2() {
+        // In every quadrant, d atan2(y, x) = (x dy - y dx) / (x² + y²)
+        for (y, x) in [(1., 2.), (1., -2.), (-1., -2.), (-1., 2.)] {
+            let dy = Float::atan2(Dual::variable(y), Dual::constant(x));
+            let dx = Float::atan2(Dual::constant(y), Dual::variable(x));
+            assert_eq!(dy.re, f64::atan2(y, x));
+            assert_relative_eq!(dy.eps, x / (x * x + y * y));
+            assert_relative_eq!(dx.eps, -y / (x * x + y * y));
+        }
+    }
+
+    #[test]
+    fn hypot() {
+        let h = Float::hypot(Dual::variable(3.), Dual::constant(4.));
+        assert_eq!(h.re, 5.);
+        assert_relative_eq!(h.eps, 0.6);
+        // Not differentiable at the origin, where the derivative is taken to be
+        // zero rather than NaN
+        let h = Float::hypot(Dual::variable(0.), Dual::variable(0.));
+        assert_eq!((h.re, h.eps), (0., 0.));
+    }
+}
@@ -0,0 +1,37 @@
+use std::fmt::{Debug, Display};
+
+/// The reasons a solve can stop before reaching the end of the time span
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SolveError<F> {
+    /// More than `maxiters` steps were attempted, counting rejected steps
+    MaxIters { t: F },
+    /// The adaptive strategy asked for a step smaller than `dtmin`, or too small
+    /// to change `t` at all
+    DtLessThanMin { t: F, dt: F },
+    /// The state picked up a NaN or infinite entry
+    NonFinite { t: F },
+    /// A linear system in an implicit or Rosenbrock step could not be solved
+    SingularMatrix { t: F },
+    /// An iterative linear solver didn't converge
+    LinearSolveFailed { t: F },
+}
+
+impl<F: Display> Display for SolveError<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolveError::MaxIters { t } => {
+                write!(f, "maximum number of iterations reached at t = {t}")
+            }
+            SolveError::DtLessThanMin { t, dt } => {
+                write!(f, "step size {dt} fell below the minimum at t = {t}")
+            }
+            SolveError::NonFinite { t } => write!(f, "state became non-finite at t = {t}"),
+            SolveError::SingularMatrix { t } => write!(f, "singular matrix at t = {t}"),
+            SolveError::LinearSolveFailed { t } => {
+                write!(f, "linear solver failed to converge at t = {t}")
+            }
+        }
+    }
+}
+
+impl<F: Debug + Display> std::error::Error for SolveError<F> {}
@@ -2,10 +2,17 @@ use nalgebra::*;
 use num_traits::Float;
 
 use crate::{
-    adaptive_strategy::AdaptiveStrategy, step_algorithm::StepAlgorithm, system::OdeSystem,
+    adaptive_strategy::AdaptiveStrategy,
+    callback::{Callback, ContinuousCallback, DiscreteCallback, Event},
+    error::SolveError,
+    jacobian::JacobianOf,
+    problem::OdeSolution,
+    step_algorithm::StepAlgorithm,
+    system::OdeSystem,
 };
 
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TSpan<F> {
     pub start: F,
     pub end: F,
@@ -17,6 +24,63 @@ impl<F> TSpan<F> {
     }
 }
 
+impl<F: Float> TSpan<F> {
+    /// One if integrating forward in time, minus one if integrating backward
+    pub fn direction(&self) -> F {
+        if self.end < self.start {
+            -F::one()
+        } else {
+            F::one()
+        }
+    }
+}
+
+/// Which states a solve keeps in its solution
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SaveAt<F> {
+    /// The start and every accepted step
+    EveryStep,
+    /// Only these times, filled in from the dense output of the steps they fall
+    /// in. Times outside the time span are left out.
+    Times(Vec<F>),
+    /// Only where the solve ends
+    End,
+}
+
+/// Options controlling how the integrator steps through the time span
+#[derive(Clone)]
+pub struct SolveOptions<F> {
+    /// Maximum number of attempted steps, including rejected ones
+    pub maxiters: usize,
+    /// Smallest step size the adaptive strategy is allowed to ask for
+    pub dtmin: F,
+    /// Times that the integrator must step exactly onto, e.g. discontinuities
+    /// in a forcing term. The end of the time span is always one of these.
+    pub tstops: Vec<F>,
+    pub saveat: SaveAt<F>,
+    /// The components of the state to save, or all of them if `None`
+    pub save_idxs: Option<Vec<usize>>,
+    /// Whether to keep the interpolant of every step, so that the solution can
+    /// be evaluated anywhere with the dense output of the step algorithm. Only
+    /// takes effect when every step of every component is saved, and the
+    /// solution interpolates linearly between the saved states otherwise.
+    pub dense: bool,
+}
+
+impl<F: Float> Default for SolveOptions<F> {
+    fn default() -> Self {
+        Self {
+            maxiters: 100_000,
+            dtmin: F::zero(),
+            tstops: vec![],
+            saveat: SaveAt::EveryStep,
+            save_idxs: None,
+            dense: true,
+        }
+    }
+}
+
 pub struct Integrator<
     'a,
     F: Scalar + Float,
@@ -27,12 +91,33 @@ pub struct Integrator<
     pub(crate) sys: &'a Sys,
     pub(crate) step_algorithm: &'a Step,
     pub(crate) adaptive_strategy: &'a AS,
+    pub(crate) adaptive_state: AS::State,
+    pub(crate) opts: SolveOptions<F>,
+    pub(crate) tspan: TSpan<F>,
+    /// The direction of time, see [`TSpan::direction`]
+    pub(crate) tdir: F,
+    /// Signed step size, i.e. negative when integrating backward
     pub(crate) dt: F,
+    pub(crate) iters: usize,
+    /// Remaining tstops, in reverse order so that the next one is at the end
+    pub(crate) tstops: Vec<F>,
     pub(crate) cache: Step::Cache,
-    pub(crate) k: usize,
+    pub(crate) t: F,
+    pub(crate) y: DVector<F>,
+    /// Where each attempted step is written, so that stepping doesn't allocate
+    pub(crate) y1: DVector<F>,
+    /// Set once a step fails, after which the iterator yields nothing
+    pub(crate) failed: bool,
+    /// Remaining times of [`SaveAt::Times`], in reverse order like `tstops`
+    pub(crate) saveat: Vec<F>,
+    /// Whether the interpolants are kept, see [`SolveOptions::dense`]
+    pub(crate) dense: bool,
+    /// The saved states and, if dense, the interpolants between them
     pub(crate) ts: Vec<F>,
     pub(crate) ys: Vec<DVector<F>>,
     pub(crate) interpolants: Vec<Step::Interpolant>,
+    pub(crate) continuous_callbacks: Vec<ContinuousCallback<'a, F>>,
+    pub(crate) discrete_callbacks: Vec<DiscreteCallback<'a, F>>,
 }
 
 impl<
@@ -42,6 +127,8 @@ impl<
     Step: StepAlgorithm<F>,
     AS: AdaptiveStrategy<F, Step::ErrorEstimate>,
 > Integrator<'a, F, Sys, Step, AS>
+where
+    Step::Jacobian: JacobianOf<F, Sys>,
 {
     pub fn new(
         sys: &'a Sys,
@@ -49,42 +136,587 @@ impl<
         adaptive_strategy: &'a AS,
         tspan: TSpan<F>,
         y0: DVector<F>,
+        opts: SolveOptions<F>,
     ) -> Self {
         let cache = step_algorithm.init_cache(sys);
-        let dt = adaptive_strategy.init_dt();
+        let tdir = tspan.direction();
+        let dt = tdir * adaptive_strategy.init_dt(sys, y0.as_view(), tspan).abs();
+        let adaptive_state = adaptive_strategy.init_state();
         let t0 = tspan.start;
+        let mut tstops: Vec<F> = opts
+            .tstops
+            .iter()
+            .copied()
+            .filter(|&t| tdir * t0 < tdir * t && tdir * t < tdir * tspan.end)
+            // A time span of zero length is finished before the first step
+            .chain((tspan.end != t0).then_some(tspan.end))
+            .collect();
+        tstops.sort_by(|a, b| (tdir * *b).partial_cmp(&(tdir * *a)).unwrap());
+        tstops.dedup();
+        let mut saveat = match &opts.saveat {
+            SaveAt::Times(times) => times
+                .iter()
+                .copied()
+                .filter(|&t| tdir * t0 <= tdir * t && tdir * t <= tdir * tspan.end)
+                .collect(),
+            _ => vec![],
+        };
+        saveat.sort_by(|a, b| (tdir * *b).partial_cmp(&(tdir * *a)).unwrap());
+        saveat.dedup();
+        let (mut ts, mut ys) = (vec![], vec![]);
+        if opts.saveat == SaveAt::EveryStep
+            || saveat.last() == Some(&t0)
+            || (opts.saveat == SaveAt::End && tstops.is_empty())
+        {
+            saveat.pop_if(|&mut t| t == t0);
+            ts.push(t0);
+            ys.push(select(&opts.save_idxs, y0.as_view()));
+        }
+        let dense = opts.dense && opts.saveat == SaveAt::EveryStep && opts.save_idxs.is_none();
         Self {
             sys,
             step_algorithm,
             adaptive_strategy,
+            adaptive_state,
+            opts,
+            tspan,
+            tdir,
             dt,
+            iters: 0,
+            tstops,
             cache,
-            k: 0,
-            ts: vec![t0],
-            ys: vec![y0],
+            t: t0,
+            y1: DVector::zeros(y0.len()),
+            y: y0,
+            failed: false,
+            saveat,
+            dense,
+            ts,
+            ys,
             interpolants: vec![],
+            continuous_callbacks: vec![],
+            discrete_callbacks: vec![],
+        }
+    }
+
+    /// Watch for the events of `callback` from here on. Continuous callbacks are
+    /// handled before discrete ones, and each kind in the order they were added.
+    pub fn with_callback(mut self, callback: impl Into<Callback<'a, F>>) -> Self {
+        match callback.into() {
+            Callback::Continuous(mut callback) => {
+                callback.reset(self.y(), self.t(), false);
+                self.continuous_callbacks.push(callback);
+            }
+            Callback::Discrete(callback) => self.discrete_callbacks.push(callback),
+        }
+        self
+    }
+
+    /// Whether the integrator has reached the end of the time span
+    pub fn finished(&self) -> bool {
+        self.tstops.is_empty()
+    }
+
+    /// The time of the last accepted step
+    pub fn t(&self) -> F {
+        self.t
+    }
+
+    /// The state at [`t`](Self::t)
+    pub fn y(&self) -> DVectorView<'_, F> {
+        self.y.as_view()
+    }
+
+    /// The signed step size the next step will try, before it is shortened to
+    /// land on a tstop
+    pub fn dt(&self) -> F {
+        self.dt
+    }
+
+    /// Set the size of the next step that is tried. Only its magnitude is used,
+    /// the sign follows the direction of time. The adaptive strategy takes over
+    /// again after that step.
+    pub fn set_dt(&mut self, dt: F) {
+        self.dt = self.tdir * dt.abs();
+    }
+
+    /// The number of attempted steps so far, including rejected ones
+    pub fn iters(&self) -> usize {
+        self.iters
+    }
+
+    /// Step until the integrator lands exactly on `t`, or on the end of the time
+    /// span if `t` is past it. Does nothing if `t` has already been passed.
+    pub fn step_until(&mut self, t: F) -> Result<(), SolveError<F>> {
+        let tdir = self.tdir;
+        if self.finished() || tdir * t <= tdir * self.t() {
+            return Ok(());
         }
+        let end = self.tstops[0];
+        let t = if tdir * t < tdir * end { t } else { end };
+        let i = self.tstops.partition_point(|&s| tdir * s > tdir * t);
+        if self.tstops.get(i) != Some(&t) {
+            self.tstops.insert(i, t);
+        }
+        while !self.finished() && tdir * self.t() < tdir * t {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    /// Step to the end of the time span, or to an event that terminates the
+    /// solve, and return the solution
+    pub fn solve(mut self) -> Result<OdeSolution<F, Step>, SolveError<F>>
+    where
+        Step: Clone,
+    {
+        while !self.finished() {
+            self.step()?;
+        }
+        let labels = self.sys.labels();
+        let labels = match &self.opts.save_idxs {
+            Some(idxs) => idxs.iter().map(|&i| labels[i].clone()).collect(),
+            None => labels,
+        };
+        Ok(OdeSolution {
+            labels,
+            tspan: self.tspan,
+            ts: self.ts,
+            ys: self.ys,
+            step_algorithm: self.step_algorithm.clone(),
+            interpolants: self.interpolants,
+            saveat: self.opts.saveat,
+            save_idxs: self.opts.save_idxs,
+            dense: self.dense,
+        })
     }
 
-    pub fn step(&mut self) {
-        let y0 = self.ys[self.k].as_view();
-        let mut y1 = DVector::zeros(self.sys.dimension());
-        let t = self.ts[self.k];
-        let (interpolant, error) =
+    /// Take one step, retrying with a smaller dt until the adaptive strategy
+    /// accepts it. Steps are shortened to land exactly on the next tstop, or
+    /// on the first event of a continuous callback.
+    pub fn step(&mut self) -> Result<(), SolveError<F>> {
+        let y0 = self.y.as_view();
+        let y1 = &mut self.y1;
+        let t = self.t;
+        let tstop = *self
+            .tstops
+            .last()
+            .expect("stepped past the end of the time span");
+        loop {
+            let hits_tstop = self.tdir * (t + self.dt) >= self.tdir * tstop;
+            let dt = if hits_tstop { tstop - t } else { self.dt };
+            if self.iters >= self.opts.maxiters {
+                return Err(SolveError::MaxIters { t });
+            }
+            self.iters += 1;
             self.step_algorithm
-                .step(&mut self.cache, self.sys, y1.as_view_mut(), y0, t, self.dt);
-        match self.adaptive_strategy.try_accept(self.dt, error, y0) {
-            Ok(dt) => {
-                self.ts.push(t + self.dt);
-                self.dt = dt;
-                self.k += 1;
-                self.ys.push(y1);
-                self.interpolants.push(interpolant);
+                .step(&mut self.cache, self.sys, y1.as_view_mut(), y0, t, dt)?;
+            let accepted = self.adaptive_strategy.try_accept(
+                &mut self.adaptive_state,
+                dt,
+                self.step_algorithm.error_estimate(&self.cache),
+                y0,
+                y1.as_view(),
+            );
+            let (Ok(new_dt) | Err(new_dt)) = accepted;
+            if new_dt.is_nan() || new_dt.abs() < self.opts.dtmin || t + new_dt == t {
+                return Err(SolveError::DtLessThanMin { t, dt: new_dt });
             }
-            Err(dt) => {
-                self.dt = dt;
-                self.step();
+            if accepted.is_ok() {
+                if y1.iter().any(|x| !x.is_finite()) {
+                    return Err(SolveError::NonFinite { t });
+                }
+                let mut t1 = if hits_tstop { tstop } else { t + dt };
+                // The dense output of the step is only needed if it is kept, a
+                // saved time falls inside the step or events are looked for
+                let saves_inside = |t1: F| {
+                    self.saveat
+                        .last()
+                        .is_some_and(|&ts| self.tdir * ts < self.tdir * t1)
+                };
+                if self.dense || saves_inside(t1) || !self.continuous_callbacks.is_empty() {
+                    self.step_algorithm.complete_interpolant(
+                        &mut self.cache,
+                        self.sys,
+                        y0,
+                        y1.as_view(),
+                        t,
+                        dt,
+                    );
+                }
+                self.dt = new_dt;
+                let mut event = None;
+                let interpolant = self.step_algorithm.interpolant(&self.cache);
+                let y = |s| {
+                    self.step_algorithm
+                        .interpolate(y0, y1.as_view(), interpolant, dt, s)
+                };
+                for (i, callback) in self.continuous_callbacks.iter().enumerate() {
+                    if let Some(s) = callback.find_event(y, y1.as_view(), t, dt)
+                        && event.is_none_or(|(_, first)| s < first)
+                    {
+                        event = Some((i, s));
+                    }
+                }
+                match event {
+                    Some((_, s)) if s < F::one() => {
+                        // Redo the step up to the event, so that the state, the
+                        // dense output and the cache all belong to the same step.
+                        // Its end agrees with the dense output of the longer
+                        // step at the root to within the local error. The
+                        // controller keeps what it made of the longer step,
+                        // whose error was the one checked, and so does the next
+                        // dt, rather than growing after a step that was only
+                        // short because of the event.
+                        t1 = t + s * dt;
+                        self.step_algorithm.step(
+                            &mut self.cache,
+                            self.sys,
+                            y1.as_view_mut(),
+                            y0,
+                            t,
+                            t1 - t,
+                        )?;
+                        if y1.iter().any(|x| !x.is_finite()) {
+                            return Err(SolveError::NonFinite { t });
+                        }
+                        if self.dense || saves_inside(t1) {
+                            self.step_algorithm.complete_interpolant(
+                                &mut self.cache,
+                                self.sys,
+                                y0,
+                                y1.as_view(),
+                                t,
+                                t1 - t,
+                            );
+                        }
+                    }
+                    _ if hits_tstop => {
+                        self.tstops.pop();
+                    }
+                    _ => {}
+                }
+                let interpolant = self.step_algorithm.interpolant(&self.cache);
+                while let Some(&ts) = self.saveat.last()
+                    && self.tdir * ts <= self.tdir * t1
+                {
+                    let y = if ts == t1 {
+                        y1.clone()
+                    } else {
+                        self.step_algorithm.interpolate(
+                            y0,
+                            y1.as_view(),
+                            interpolant,
+                            t1 - t,
+                            (ts - t) / (t1 - t),
+                        )
+                    };
+                    self.ts.push(ts);
+                    self.ys.push(select(&self.opts.save_idxs, y.as_view()));
+                    self.saveat.pop();
+                }
+                std::mem::swap(&mut self.y, &mut self.y1);
+                self.t = t1;
+                self.save_step();
+                if let Some((i, _)) = event {
+                    let mut event = Event::new(t1, self.y.clone());
+                    self.continuous_callbacks[i].affect(&mut event);
+                    self.apply(event);
+                }
+                for i in 0..self.discrete_callbacks.len() {
+                    if self.discrete_callbacks[i].condition(self.y(), t1) {
+                        let mut event = Event::new(t1, self.y.clone());
+                        self.discrete_callbacks[i].affect(&mut event);
+                        self.apply(event);
+                    }
+                }
+                for (i, callback) in self.continuous_callbacks.iter_mut().enumerate() {
+                    callback.reset(self.y.as_view(), t1, event.is_some_and(|(j, _)| i == j));
+                }
+                if self.finished() && self.opts.saveat == SaveAt::End {
+                    self.ts.push(self.t);
+                    self.ys.push(select(&self.opts.save_idxs, self.y.as_view()));
+                }
+                return Ok(());
             }
+            self.step_algorithm.step_rejected(&mut self.cache);
+            self.dt = new_dt;
+        }
+    }
+
+    /// Carry out what the affect of a callback did to `event`
+    fn apply(&mut self, event: Event<F>) {
+        if event.modified {
+            self.y = event.y;
+            self.save_step();
+            self.step_algorithm.state_modified(&mut self.cache);
+        }
+        if let Some(dt) = event.dt {
+            self.set_dt(dt);
+        }
+        if event.terminated {
+            self.tstops.clear();
+        }
+    }
+
+    /// Save the current state if every step is saved, with the interpolant of
+    /// the last step if dense
+    fn save_step(&mut self) {
+        if self.opts.saveat != SaveAt::EveryStep {
+            return;
+        }
+        if self.dense {
+            let interpolant = self.step_algorithm.interpolant(&self.cache);
+            self.interpolants.push(interpolant.clone());
+        }
+        self.ts.push(self.t);
+        self.ys.push(select(&self.opts.save_idxs, self.y.as_view()));
+    }
+}
+
+/// The components of `y` in `idxs`, or all of them
+fn select<F: Scalar>(idxs: &Option<Vec<usize>>, y: DVectorView<F>) -> DVector<F> {
+    match idxs {
+        Some(idxs) => DVector::from_iterator(idxs.len(), idxs.iter().map(|&i| y[i].clone())),
+        None => y.clone_owned(),
+    }
+}
+
+/// Steps the integrator, yielding `(t, y, dt)` for each accepted step, where
+/// `dt` is the size of the step that ended at `t`. Stops at the end of the time
+/// span, or after yielding the first error.
+impl<
+    F: Float + Scalar,
+    Sys: OdeSystem<F>,
+    Step: StepAlgorithm<F>,
+    AS: AdaptiveStrategy<F, Step::ErrorEstimate>,
+> Iterator for Integrator<'_, F, Sys, Step, AS>
+where
+    Step::Jacobian: JacobianOf<F, Sys>,
+{
+    type Item = Result<(F, DVector<F>, F), SolveError<F>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished() || self.failed {
+            return None;
+        }
+        let t0 = self.t();
+        if let Err(e) = self.step() {
+            self.failed = true;
+            return Some(Err(e));
+        }
+        Some(Ok((self.t(), self.y().clone_owned(), self.t() - t0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+    use crate::{
+        ConstantStep, IntegralController, JacobianReuse, OdeProblem,
+        linear_solver::tests::Linear,
+        step_algorithm::{DP5, Rosenbrock23},
+    };
+
+    /// `y'' = -y` as a first order system, with solution `(cos t, -sin t)`
+    fn oscillator() -> Linear {
+        Linear(DMatrix::from_row_slice(2, 2, &[0., 1., -1., 0.]))
+    }
+
+    fn controller() -> IntegralController<f64> {
+        IntegralController::new(1e-10, 1e-10, 4)
+    }
+
+    #[test]
+    fn lands_on_tstops_and_the_end() {
+        let problem = OdeProblem::new(oscillator(), dvector![1., 0.], TSpan::new(0., 1.3));
+        let opts = SolveOptions {
+            tstops: vec![0.7, 0.123, 5., -1.],
+            ..Default::default()
+        };
+        let solution = problem
+            .solve_with_options(&DP5, &controller(), opts)
+            .unwrap();
+        assert_eq!(solution.ts()[0], 0.);
+        assert_eq!(*solution.ts().last().unwrap(), 1.3);
+        assert!(solution.ts().contains(&0.123));
+        assert!(solution.ts().contains(&0.7));
+        assert!(solution.ts().iter().all(|&t| t <= 1.3));
+        let expected = dvector![f64::cos(1.3), -f64::sin(1.3)];
+        assert_relative_eq!(*solution.ys().last().unwrap(), expected, epsilon = 1e-8);
+    }
+
+    #[test]
+    fn zero_length_time_span() {
+        for saveat in [SaveAt::EveryStep, SaveAt::End, SaveAt::Times(vec![1.])] {
+            let problem = OdeProblem::new(oscillator(), dvector![1., 0.], TSpan::new(1., 1.));
+            let opts = SolveOptions {
+                saveat,
+                ..Default::default()
+            };
+            let solution = problem
+                .solve_with_options(&DP5, &controller(), opts)
+                .unwrap();
+            assert_eq!(solution.ts(), [1.]);
+            assert_eq!(solution.ys(), [dvector![1., 0.]]);
+        }
+    }
+
+    #[test]
+    fn backward_matches_forward() {
+        let y0 = dvector![1., 0.];
+        let forward = OdeProblem::new(oscillator(), y0.clone(), TSpan::new(0., 2.))
+            .solve(&DP5, &controller())
+            .unwrap();
+        let y1 = forward.ys().last().unwrap().clone();
+        let backward = OdeProblem::new(oscillator(), y1, TSpan::new(2., 0.))
+            .solve(&DP5, &controller())
+            .unwrap();
+        assert_eq!(*backward.ts().last().unwrap(), 0.);
+        assert!(backward.ts().windows(2).all(|w| w[1] < w[0]));
+        assert_relative_eq!(*backward.ys().last().unwrap(), y0, epsilon = 1e-8);
+        for t in [0.05, 0.5, 1.234, 1.99] {
+            assert_relative_eq!(
+                backward.solution_at(t),
+                forward.solution_at(t),
+                epsilon = 1e-8
+            );
         }
     }
+
+    #[test]
+    fn step_until_and_set_dt() {
+        let problem = OdeProblem::new(oscillator(), dvector![1., 0.], TSpan::new(0., 2.));
+        let controller = controller();
+        let mut integrator = problem.init(&DP5, &controller, Default::default());
+        integrator.step_until(0.5).unwrap();
+        assert_eq!(integrator.t(), 0.5);
+        let expected = dvector![f64::cos(0.5), -f64::sin(0.5)];
+        assert_relative_eq!(integrator.y().into_owned(), expected, epsilon = 1e-8);
+        // Times already passed are ignored
+        integrator.step_until(0.25).unwrap();
+        assert_eq!(integrator.t(), 0.5);
+
+        integrator.set_dt(-0.015625);
+        assert_eq!(integrator.dt(), 0.015625);
+        let (t, _, dt) = integrator.next().unwrap().unwrap();
+        assert_eq!((t, dt), (0.515625, 0.015625));
+        let mut last = t;
+        for _ in 0..3 {
+            let (t, y, dt) = integrator.next().unwrap().unwrap();
+            assert_eq!(dt, t - last);
+            assert_eq!(y, integrator.y());
+            last = t;
+        }
+        // Times past the end stop at the end
+        integrator.step_until(3.).unwrap();
+        assert_eq!(integrator.t(), 2.);
+        assert!(integrator.finished());
+        assert!(integrator.next().is_none());
+    }
+
+    /// `y' = -y²`, whose Jacobian depends on the state
+    struct Quadratic;
+
+    impl OdeSystem<f64> for Quadratic {
+        fn dimension(&self) -> usize {
+            1
+        }
+
+        fn labels(&self) -> Vec<String> {
+            vec!["y".to_string()]
+        }
+
+        fn vfield(&self, mut out: DVectorViewMut<f64>, y: DVectorView<f64>, _t: f64) {
+            out[0] = -y[0] * y[0];
+        }
+    }
+
+    /// Set the state to 2 at t = 0.5 and check that the rest of the solve is
+    /// the same as a solve starting there, which it isn't if the step
+    /// algorithm keeps anything from before the change
+    fn restarts_after_modification<SA: StepAlgorithm<f64, Jacobian: JacobianOf<f64, Quadratic>>>(
+        step_algorithm: &SA,
+    ) {
+        let strategy = ConstantStep(0.125);
+        let problem = OdeProblem::new(Quadratic, dvector![1.], TSpan::new(0., 1.));
+        let opts = SolveOptions {
+            tstops: vec![0.5],
+            ..Default::default()
+        };
+        let mut integrator =
+            problem
+                .init(step_algorithm, &strategy, opts)
+                .with_callback(DiscreteCallback::new(
+                    |_, t| t == 0.5,
+                    |event| event.y_mut()[0] = 2.,
+                ));
+        integrator.step_until(1.).unwrap();
+        let restarted = OdeProblem::new(Quadratic, dvector![2.], TSpan::new(0.5, 1.));
+        let mut expected = restarted.init(step_algorithm, &strategy, Default::default());
+        expected.step_until(1.).unwrap();
+        assert_eq!(integrator.y(), expected.y());
+    }
+
+    #[test]
+    fn modified_state_drops_the_fsal_stage() {
+        restarts_after_modification(&DP5);
+    }
+
+    #[test]
+    fn modified_state_drops_the_jacobian() {
+        let rosenbrock = Rosenbrock23::new().with_jacobian_reuse(JacobianReuse::new(100, 1e9));
+        restarts_after_modification(&rosenbrock);
+    }
+
+    #[test]
+    fn saveat_modes() {
+        let problem = OdeProblem::new(oscillator(), dvector![1., 0.], TSpan::new(0., 2.));
+        let solve = |opts| {
+            problem
+                .solve_with_options(&DP5, &controller(), opts)
+                .unwrap()
+        };
+        let every_step = solve(SolveOptions::default());
+        assert!(every_step.is_dense());
+
+        let times = vec![1.5, -1., 0., 0.3, 3., 2.];
+        let at_times = solve(SolveOptions {
+            saveat: SaveAt::Times(times),
+            ..Default::default()
+        });
+        assert!(!at_times.is_dense());
+        assert_eq!(at_times.ts(), [0., 0.3, 1.5, 2.]);
+        for (&t, y) in at_times.ts().iter().zip(at_times.ys()) {
+            assert_relative_eq!(*y, every_step.solution_at(t), max_relative = 1e-14);
+        }
+
+        let at_end = solve(SolveOptions {
+            saveat: SaveAt::End,
+            ..Default::default()
+        });
+        assert_eq!(at_end.ts(), [2.]);
+        assert_eq!(at_end.ys(), [every_step.ys().last().unwrap().clone()]);
+
+        let second = solve(SolveOptions {
+            save_idxs: Some(vec![1]),
+            ..Default::default()
+        });
+        assert!(!second.is_dense());
+        assert_eq!(second.ts(), every_step.ts());
+        for (y, full) in second.ys().iter().zip(every_step.ys()) {
+            assert_eq!(*y, dvector![full[1]]);
+        }
+
+        let sparse = solve(SolveOptions {
+            dense: false,
+            ..Default::default()
+        });
+        assert!(!sparse.is_dense());
+        let (t0, t1) = (sparse.ts()[1], sparse.ts()[2]);
+        let midpoint = (sparse.ys()[1].clone() + &sparse.ys()[2]) / 2.;
+        assert_relative_eq!(sparse.solution_at((t0 + t1) / 2.), midpoint);
+    }
 }
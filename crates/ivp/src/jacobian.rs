@@ -0,0 +1,482 @@
+use my_linalg::kernels::{axpy, max_abs};
+use nalgebra::*;
+use num_traits::Float;
+
+use crate::{
+    dual::Dual,
+    sparse::{ColumnColoring, SparseMatrix},
+    system::OdeSystem,
+};
+
+/// How a step algorithm that needs the Jacobian of the vector field gets it.
+/// What it computes the Jacobian of is up to [`JacobianOf`], so that a strategy
+/// can ask more of a system than [`OdeSystem<F>`].
+pub trait JacobianStrategy<F: Scalar + Float> {
+    /// Scratch space, kept by the step algorithm so that computing a Jacobian
+    /// doesn't allocate
+    type Cache;
+
+    fn init_cache<S: OdeSystem<F> + ?Sized>(&self, sys: &S) -> Self::Cache;
+}
+
+/// A [`JacobianStrategy`] for systems of type `S`
+pub trait JacobianOf<F: Scalar + Float, S: OdeSystem<F> + ?Sized>: JacobianStrategy<F> {
+    fn jacobian(
+        &self,
+        cache: &mut Self::Cache,
+        sys: &S,
+        out: DMatrixViewMut<F>,
+        y: DVectorView<F>,
+        t: F,
+    );
+
+    /// Fill in a Jacobian whose pattern contains
+    /// [`OdeSystem::jacobian_sparsity`]. `coloring` must come from the pattern
+    /// of `out`.
+    fn sparse_jacobian(
+        &self,
+        cache: &mut Self::Cache,
+        sys: &S,
+        coloring: &ColumnColoring,
+        out: &mut SparseMatrix<F>,
+        y: DVectorView<F>,
+        t: F,
+    );
+
+    /// Set `out` to the Jacobian at `(y, t)` times `v`, where `fy` is `vfield`
+    /// at `(y, t)`
+    #[allow(clippy::too_many_arguments)]
+    fn jvp(
+        &self,
+        cache: &mut Self::Cache,
+        sys: &S,
+        out: DVectorViewMut<F>,
+        y: DVectorView<F>,
+        fy: DVectorView<F>,
+        t: F,
+        v: DVectorView<F>,
+    );
+}
+
+/// Use [`OdeSystem::jacobian`], [`OdeSystem::sparse_jacobian`] and
+/// [`OdeSystem::jvp`], which are forward differences unless the system provides
+/// its own. The cache is the scratch space of those forward differences.
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SystemJacobian;
+
+impl<F: Scalar + Float> JacobianStrategy<F> for SystemJacobian {
+    type Cache = FiniteDifferenceCache<F>;
+
+    fn init_cache<S: OdeSystem<F> + ?Sized>(&self, sys: &S) -> Self::Cache {
+        FiniteDifference::Forward.init_cache(sys)
+    }
+}
+
+impl<F: Scalar + Float, S: OdeSystem<F> + ?Sized> JacobianOf<F, S> for SystemJacobian {
+    fn jacobian(
+        &self,
+        cache: &mut Self::Cache,
+        sys: &S,
+        out: DMatrixViewMut<F>,
+        y: DVectorView<F>,
+        t: F,
+    ) {
+        sys.jacobian(out, y, t, cache);
+    }
+
+    fn sparse_jacobian(
+        &self,
+        cache: &mut Self::Cache,
+        sys: &S,
+        coloring: &ColumnColoring,
+        out: &mut SparseMatrix<F>,
+        y: DVectorView<F>,
+        t: F,
+    ) {
+        sys.sparse_jacobian(out, y, t, coloring, cache);
+    }
+
+    fn jvp(
+        &self,
+        cache: &mut Self::Cache,
+        sys: &S,
+        out: DVectorViewMut<F>,
+        y: DVectorView<F>,
+        fy: DVectorView<F>,
+        t: F,
+        v: DVectorView<F>,
+    ) {
+        sys.jvp(out, y, fy, t, v, cache);
+    }
+}
+
+/// Approximate the Jacobian by perturbing the components of `y`, with each
+/// perturbation scaled to the size of its component. Sparse Jacobians perturb
+/// every column of a color at once.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FiniteDifference {
+    /// One extra evaluation of `vfield` per column, first order accurate
+    #[default]
+    Forward,
+    /// Two evaluations of `vfield` per column, second order accurate
+    Central,
+}
+
+pub struct FiniteDifferenceCache<F> {
+    yp: DVector<F>,
+    ym: DVector<F>,
+    f0: DVector<F>,
+    f1: DVector<F>,
+    width: DVector<F>,
+}
+
+impl FiniteDifference {
+    /// The step that balances truncation against roundoff error
+    fn rel_step<F: Float>(&self) -> F {
+        match self {
+            FiniteDifference::Forward => Float::sqrt(F::epsilon()),
+            FiniteDifference::Central => Float::cbrt(F::epsilon()),
+        }
+    }
+
+    /// Perturb the columns in each group together, calling `store` with each
+    /// column of the group, the two evaluations of `vfield` and the distance
+    /// between them
+    fn probe<F: Scalar + Float, S: OdeSystem<F> + ?Sized, G: AsRef<[usize]>>(
+        &self,
+        cache: &mut FiniteDifferenceCache<F>,
+        sys: &S,
+        groups: impl Iterator<Item = G>,
+        y: DVectorView<F>,
+        t: F,
+        mut store: impl FnMut(usize, &DVector<F>, &DVector<F>, F),
+    ) {
+        let rel_step = self.rel_step::<F>();
+        let FiniteDifferenceCache {
+            yp,
+            ym,
+            f0,
+            f1,
+            width,
+        } = cache;
+        yp.copy_from(&y);
+        ym.copy_from(&y);
+        if *self == FiniteDifference::Forward {
+            sys.vfield(f0.as_view_mut(), y, t);
+        }
+        for group in groups {
+            let group = group.as_ref();
+            for &j in group {
+                let h = rel_step * Float::max(Float::abs(y[j]), F::one());
+                // Use the steps that are actually representable
+                yp[j] = y[j] + h;
+                ym[j] = y[j] - h;
+                width[j] = match self {
+                    FiniteDifference::Forward => yp[j] - y[j],
+                    FiniteDifference::Central => yp[j] - ym[j],
+                };
+            }
+            sys.vfield(f1.as_view_mut(), yp.as_view(), t);
+            if *self == FiniteDifference::Central {
+                sys.vfield(f0.as_view_mut(), ym.as_view(), t);
+            }
+            for &j in group {
+                store(j, f1, f0, width[j]);
+                yp[j] = y[j];
+                ym[j] = y[j];
+            }
+        }
+    }
+}
+
+impl<F: Scalar + Float> JacobianStrategy<F> for FiniteDifference {
+    type Cache = FiniteDifferenceCache<F>;
+
+    fn init_cache<S: OdeSystem<F> + ?Sized>(&self, sys: &S) -> Self::Cache {
+        let n = sys.dimension();
+        FiniteDifferenceCache {
+            yp: DVector::zeros(n),
+            ym: DVector::zeros(n),
+            f0: DVector::zeros(n),
+            f1: DVector::zeros(n),
+            width: DVector::zeros(n),
+        }
+    }
+}
+
+impl<F: Scalar + Float, S: OdeSystem<F> + ?Sized> JacobianOf<F, S> for FiniteDifference {
+    fn jacobian(
+        &self,
+        cache: &mut Self::Cache,
+        sys: &S,
+        mut out: DMatrixViewMut<F>,
+        y: DVectorView<F>,
+        t: F,
+    ) {
+        let n = sys.dimension();
+        let columns = (0..n).map(|j| [j]);
+        self.probe(cache, sys, columns, y, t, |j, f1, f0, h| {
+            for i in 0..n {
+                out[(i, j)] = (f1[i] - f0[i]) / h;
+            }
+        });
+    }
+
+    fn sparse_jacobian(
+        &self,
+        cache: &mut Self::Cache,
+        sys: &S,
+        coloring: &ColumnColoring,
+        out: &mut SparseMatrix<F>,
+        y: DVectorView<F>,
+        t: F,
+    ) {
+        let (pattern, values) = out.parts_mut();
+        self.probe(
+            cache,
+            sys,
+            coloring.groups().iter(),
+            y,
+            t,
+            |j, f1, f0, h| {
+                for p in pattern.col_range(j) {
+                    let i = pattern.row(p);
+                    values[p] = (f1[i] - f0[i]) / h;
+                }
+            },
+        );
+    }
+
+    fn jvp(
+        &self,
+        cache: &mut Self::Cache,
+        sys: &S,
+        mut out: DVectorViewMut<F>,
+        y: DVectorView<F>,
+        fy: DVectorView<F>,
+        t: F,
+        v: DVectorView<F>,
+    ) {
+        let vnorm = max_abs(v.as_slice());
+        if vnorm == F::zero() {
+            out.fill(F::zero());
+            return;
+        }
+        // Scale the step so that no component of y moves by more than it would
+        // for a single column of the Jacobian
+        let h = self.rel_step::<F>() * Float::max(max_abs(y.as_slice()), F::one()) / vnorm;
+        let FiniteDifferenceCache { yp, f0, .. } = cache;
+        yp.copy_from(&y);
+        axpy(h, v.as_slice(), yp.as_mut_slice());
+        sys.vfield(out.as_view_mut(), yp.as_view(), t);
+        match self {
+            FiniteDifference::Forward => {
+                for i in 0..out.len() {
+                    out[i] = (out[i] - fy[i]) / h;
+                }
+            }
+            FiniteDifference::Central => {
+                yp.copy_from(&y);
+                axpy(-h, v.as_slice(), yp.as_mut_slice());
+                sys.vfield(f0.as_view_mut(), yp.as_view(), t);
+                for i in 0..out.len() {
+                    out[i] = (out[i] - f0[i]) / (h + h);
+                }
+            }
+        }
+    }
+}
+
+/// Compute the Jacobian exactly, up to roundoff, by evaluating `vfield` on
+/// [`Dual`] numbers with the dual part of `y` set to one for a single
+/// column, or for every column of a color when the Jacobian is sparse. It works
+/// for systems that implement [`OdeSystem`] for `Dual<F>` as well as `F`, which
+/// systems generic over the scalar type do.
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ForwardDiff;
+
+pub struct ForwardDiffCache<F> {
+    yd: DVector<Dual<F>>,
+    fd: DVector<Dual<F>>,
+}
+
+impl ForwardDiff {
+    fn probe<F: Scalar + Float, S: OdeSystem<Dual<F>> + ?Sized, G: AsRef<[usize]>>(
+        &self,
+        cache: &mut ForwardDiffCache<F>,
+        sys: &S,
+        groups: impl Iterator<Item = G>,
+        y: DVectorView<F>,
+        t: F,
+        mut store: impl FnMut(usize, &DVector<Dual<F>>),
+    ) {
+        let ForwardDiffCache { yd, fd } = cache;
+        for i in 0..y.len() {
+            yd[i] = Dual::constant(y[i]);
+        }
+        for group in groups {
+            let group = group.as_ref();
+            for &j in group {
+                yd[j].eps = F::one();
+            }
+            sys.vfield(fd.as_view_mut(), yd.as_view(), Dual::constant(t));
+            for &j in group {
+                store(j, fd);
+                yd[j].eps = F::zero();
+            }
+        }
+    }
+}
+
+impl<F: Scalar + Float> JacobianStrategy<F> for ForwardDiff {
+    type Cache = ForwardDiffCache<F>;
+
+    fn init_cache<S: OdeSystem<F> + ?Sized>(&self, sys: &S) -> Self::Cache {
+        let n = sys.dimension();
+        ForwardDiffCache {
+            yd: DVector::zeros(n),
+            fd: DVector::zeros(n),
+        }
+    }
+}
+
+impl<F, S> JacobianOf<F, S> for ForwardDiff
+where
+    F: Scalar + Float,
+    S: OdeSystem<F> + OdeSystem<Dual<F>> + ?Sized,
+{
+    fn jacobian(
+        &self,
+        cache: &mut Self::Cache,
+        sys: &S,
+        mut out: DMatrixViewMut<F>,
+        y: DVectorView<F>,
+        t: F,
+    ) {
+        let n = OdeSystem::<F>::dimension(sys);
+        let columns = (0..n).map(|j| [j]);
+        self.probe(cache, sys, columns, y, t, |j, fd| {
+            for i in 0..n {
+                out[(i, j)] = fd[i].eps;
+            }
+        });
+    }
+
+    fn sparse_jacobian(
+        &self,
+        cache: &mut Self::Cache,
+        sys: &S,
+        coloring: &ColumnColoring,
+        out: &mut SparseMatrix<F>,
+        y: DVectorView<F>,
+        t: F,
+    ) {
+        let (pattern, values) = out.parts_mut();
+        self.probe(cache, sys, coloring.groups().iter(), y, t, |j, fd| {
+            for p in pattern.col_range(j) {
+                values[p] = fd[pattern.row(p)].eps;
+            }
+        });
+    }
+
+    fn jvp(
+        &self,
+        cache: &mut Self::Cache,
+        sys: &S,
+        mut out: DVectorViewMut<F>,
+        y: DVectorView<F>,
+        _fy: DVectorView<F>,
+        t: F,
+        v: DVectorView<F>,
+    ) {
+        // Seeding the dual parts with v gives the directional derivative in
+        // one evaluation
+        let ForwardDiffCache { yd, fd } = cache;
+        for i in 0..y.len() {
+            yd[i] = Dual::new(y[i], v[i]);
+        }
+        OdeSystem::<Dual<F>>::vfield(sys, fd.as_view_mut(), yd.as_view(), Dual::constant(t));
+        for i in 0..out.len() {
+            out[i] = fd[i].eps;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+    use crate::sparse::SparsityPattern;
+
+    /// `y_i' = y_i² + t sin y_{i+1}`, generic over the scalar so that it can
+    /// be evaluated on dual numbers
+    struct Chain(usize);
+
+    impl<F: Scalar + Float> OdeSystem<F> for Chain {
+        fn dimension(&self) -> usize {
+            self.0
+        }
+
+        fn labels(&self) -> Vec<String> {
+            (0..self.0).map(|i| format!("y{i}")).collect()
+        }
+
+        fn vfield(&self, mut out: DVectorViewMut<F>, y: DVectorView<F>, t: F) {
+            for i in 0..self.0 {
+                out[i] = y[i] * y[i];
+                if i + 1 < self.0 {
+                    out[i] = out[i] + t * Float::sin(y[i + 1]);
+                }
+            }
+        }
+    }
+
+    fn exact(y: &DVector<f64>, t: f64) -> DMatrix<f64> {
+        let n = y.len();
+        DMatrix::from_fn(n, n, |i, j| {
+            if j == i {
+                2. * y[i]
+            } else if j == i + 1 {
+                t * f64::cos(y[j])
+            } else {
+                0.
+            }
+        })
+    }
+
+    #[test]
+    fn forward_diff_is_exact() {
+        let sys = Chain(4);
+        let (y, t) = (DVector::from_vec(vec![0.3, -1.2, 2., 0.7]), 1.5);
+        let expected = exact(&y, t);
+        let mut cache = ForwardDiff.init_cache(&sys);
+
+        let mut dense = DMatrix::zeros(4, 4);
+        ForwardDiff.jacobian(&mut cache, &sys, dense.as_view_mut(), y.as_view(), t);
+        assert_relative_eq!(dense, expected, max_relative = 1e-15);
+
+        let pattern = SparsityPattern::banded(4, 0, 1);
+        let coloring = ColumnColoring::new(&pattern);
+        let mut sparse = SparseMatrix::zeros(pattern);
+        ForwardDiff.sparse_jacobian(&mut cache, &sys, &coloring, &mut sparse, y.as_view(), t);
+        assert_relative_eq!(sparse.to_dense(), expected, max_relative = 1e-15);
+
+        let v = DVector::from_vec(vec![1., 2., -1., 0.5]);
+        let mut jv = DVector::zeros(4);
+        let fy = DVector::zeros(4);
+        ForwardDiff.jvp(
+            &mut cache,
+            &sys,
+            jv.as_view_mut(),
+            y.as_view(),
+            fy.as_view(),
+            t,
+            v.as_view(),
+        );
+        assert_relative_eq!(jv, &expected * &v, max_relative = 1e-15);
+    }
+}
@@ -1,11 +1,25 @@
 pub mod adaptive_strategy;
+pub mod callback;
+pub mod dual;
+pub mod error;
 pub mod integrator;
+pub mod jacobian;
+pub mod linear_solver;
 pub mod problem;
+pub mod sparse;
 pub mod step_algorithm;
 pub mod system;
+pub mod tolerance;
 
 pub use adaptive_strategy::*;
+pub use callback::*;
+pub use dual::*;
+pub use error::*;
 pub use integrator::*;
+pub use jacobian::*;
+pub use linear_solver::*;
 pub use problem::*;
+pub use sparse::*;
 pub use step_algorithm::*;
 pub use system::*;
+pub use tolerance::*;
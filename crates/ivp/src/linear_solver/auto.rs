@@ -0,0 +1,82 @@
+use nalgebra::*;
+use num_traits::Float;
+
+use super::{DenseSolver, DenseSolverCache, LinearSolver, SparseSolver, SparseSolverCache};
+use crate::{jacobian::JacobianOf, system::OdeSystem};
+
+/// [`SparseSolver`] if the system gives a
+/// [`jacobian_sparsity`](OdeSystem::jacobian_sparsity), otherwise
+/// [`DenseSolver`]
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AutoSolver;
+
+impl AutoSolver {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+pub enum AutoSolverCache<F: ComplexField> {
+    Dense(DenseSolverCache<F>),
+    Sparse(Box<SparseSolverCache<F>>),
+}
+
+impl<F: Float + ComplexField<RealField = F>> LinearSolver<F> for AutoSolver {
+    type Cache = AutoSolverCache<F>;
+
+    fn init_cache<S: OdeSystem<F>>(&self, sys: &S) -> Self::Cache {
+        if sys.jacobian_sparsity().is_some() {
+            AutoSolverCache::Sparse(Box::new(SparseSolver.init_cache(sys)))
+        } else {
+            AutoSolverCache::Dense(DenseSolver.init_cache(sys))
+        }
+    }
+
+    fn update_jacobian<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+        &self,
+        cache: &mut Self::Cache,
+        jacobian: &J,
+        jacobian_cache: &mut J::Cache,
+        sys: &S,
+        y: DVectorView<F>,
+        t: F,
+    ) {
+        match cache {
+            AutoSolverCache::Dense(c) => {
+                DenseSolver.update_jacobian(c, jacobian, jacobian_cache, sys, y, t)
+            }
+            AutoSolverCache::Sparse(c) => {
+                SparseSolver.update_jacobian(c, jacobian, jacobian_cache, sys, y, t)
+            }
+        }
+    }
+
+    fn factor<S: OdeSystem<F>>(&self, cache: &mut Self::Cache, sys: &S, shift: F) -> bool {
+        match cache {
+            AutoSolverCache::Dense(c) => DenseSolver.factor(c, sys, shift),
+            AutoSolverCache::Sparse(c) => SparseSolver.factor(c, sys, shift),
+        }
+    }
+
+    fn solve<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+        &self,
+        cache: &mut Self::Cache,
+        jacobian: &J,
+        jacobian_cache: &mut J::Cache,
+        sys: &S,
+        b: DVectorViewMut<F>,
+    ) -> bool {
+        match cache {
+            AutoSolverCache::Dense(c) => DenseSolver.solve(c, jacobian, jacobian_cache, sys, b),
+            AutoSolverCache::Sparse(c) => SparseSolver.solve(c, jacobian, jacobian_cache, sys, b),
+        }
+    }
+
+    fn mass_mul(&self, cache: &Self::Cache, x: DVectorView<F>, out: DVectorViewMut<F>) {
+        match cache {
+            AutoSolverCache::Dense(c) => DenseSolver.mass_mul(c, x, out),
+            AutoSolverCache::Sparse(c) => SparseSolver.mass_mul(c, x, out),
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use nalgebra::*;
+use num_traits::Float;
+
+use super::LinearSolver;
+use crate::{
+    jacobian::JacobianOf,
+    sparse::{ColumnColoring, SparseMatrix, SparsityPattern},
+    system::OdeSystem,
+};
+
+/// LU with partial pivoting of a `W` with `lower` subdiagonals and `upper`
+/// superdiagonals, as in LAPACK's `gbtrf`. The Jacobian is found with the
+/// banded [`ColumnColoring`], so it takes `lower + upper + 1` evaluations of
+/// the vector field whatever the dimension.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BandedSolver {
+    lower: usize,
+    upper: usize,
+}
+
+impl BandedSolver {
+    pub fn new(lower: usize, upper: usize) -> Self {
+        Self { lower, upper }
+    }
+}
+
+pub struct BandedSolverCache<F> {
+    jacobian: SparseMatrix<F>,
+    coloring: ColumnColoring,
+    mass_matrix: SparseMatrix<F>,
+    lu: BandedLU<F>,
+    singular: bool,
+}
+
+impl<F: Float + ComplexField<RealField = F>> LinearSolver<F> for BandedSolver {
+    type Cache = BandedSolverCache<F>;
+
+    fn init_cache<S: OdeSystem<F>>(&self, sys: &S) -> Self::Cache {
+        let n = sys.dimension();
+        let pattern = SparsityPattern::banded(n, self.lower, self.upper);
+        let mass_matrix = sys.sparse_mass_matrix();
+        for (i, j) in mass_matrix.pattern().entries() {
+            assert!(
+                i <= j + self.lower && j <= i + self.upper,
+                "mass matrix entry ({i}, {j}) is outside of the band"
+            );
+        }
+        BandedSolverCache {
+            coloring: ColumnColoring::new(&pattern),
+            jacobian: SparseMatrix::zeros(pattern),
+            mass_matrix,
+            lu: BandedLU::new(n, self.lower, self.upper),
+            singular: true,
+        }
+    }
+
+    fn update_jacobian<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+        &self,
+        cache: &mut Self::Cache,
+        jacobian: &J,
+        jacobian_cache: &mut J::Cache,
+        sys: &S,
+        y: DVectorView<F>,
+        t: F,
+    ) {
+        jacobian.sparse_jacobian(
+            jacobian_cache,
+            sys,
+            &cache.coloring,
+            &mut cache.jacobian,
+            y,
+            t,
+        );
+    }
+
+    fn factor<S: OdeSystem<F>>(&self, cache: &mut Self::Cache, _sys: &S, shift: F) -> bool {
+        let lu = &mut cache.lu;
+        lu.ab.fill(F::zero());
+        let jacobian = &cache.jacobian;
+        for ((i, j), &x) in jacobian.pattern().entries().zip(jacobian.values()) {
+            *lu.get_mut(i, j) = x;
+        }
+        let mass_matrix = &cache.mass_matrix;
+        for ((i, j), &m) in mass_matrix.pattern().entries().zip(mass_matrix.values()) {
+            let w = lu.get_mut(i, j);
+            *w -= shift * m;
+        }
+        cache.singular = !lu.factor();
+        !cache.singular
+    }
+
+    fn solve<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+        &self,
+        cache: &mut Self::Cache,
+        _jacobian: &J,
+        _jacobian_cache: &mut J::Cache,
+        _sys: &S,
+        b: DVectorViewMut<F>,
+    ) -> bool {
+        if cache.singular {
+            return false;
+        }
+        cache.lu.solve_mut(b);
+        true
+    }
+
+    fn mass_mul(&self, cache: &Self::Cache, x: DVectorView<F>, out: DVectorViewMut<F>) {
+        cache.mass_matrix.mul_to(x, out);
+    }
+}
+
+/// A band matrix in LAPACK's layout, with `lower` extra superdiagonals for
+/// the fill from row swaps
+struct BandedLU<F> {
+    n: usize,
+    lower: usize,
+    upper: usize,
+    /// Column major with `2 lower + upper + 1` rows, entry `(i, j)` of the
+    /// matrix is at row `lower + upper + i - j` of column `j`
+    ab: Vec<F>,
+    pivots: Vec<usize>,
+}
+
+impl<F: Scalar + Float> BandedLU<F> {
+    fn new(n: usize, lower: usize, upper: usize) -> Self {
+        Self {
+            n,
+            lower,
+            upper,
+            ab: vec![F::zero(); n * (2 * lower + upper + 1)],
+            pivots: vec![0; n],
+        }
+    }
+
+    fn index(&self, i: usize, j: usize) -> usize {
+        debug_assert!(i <= j + self.lower && j <= i + self.lower + self.upper);
+        j * (2 * self.lower + self.upper + 1) + self.lower + self.upper + i - j
+    }
+
+    fn get(&self, i: usize, j: usize) -> F {
+        self.ab[self.index(i, j)]
+    }
+
+    fn get_mut(&mut self, i: usize, j: usize) -> &mut F {
+        let p = self.index(i, j);
+        &mut self.ab[p]
+    }
+
+    /// Factorize in place, returning false if the matrix is singular
+    fn factor(&mut self) -> bool {
+        let n = self.n;
+        // The last column that the row swaps so far have filled in
+        let mut last = 0;
+        for j in 0..n {
+            let below = self.lower.min(n - 1 - j);
+            let mut p = j;
+            for i in j + 1..=j + below {
+                if Float::abs(self.get(i, j)) > Float::abs(self.get(p, j)) {
+                    p = i;
+                }
+            }
+            self.pivots[j] = p;
+            let pivot = self.get(p, j);
+            if pivot == F::zero() || !pivot.is_finite() {
+                return false;
+            }
+            last = last.max((p + self.upper).min(n - 1));
+            if p != j {
+                for k in j..=last {
+                    let (a, b) = (self.index(j, k), self.index(p, k));
+                    self.ab.swap(a, b);
+                }
+            }
+            for i in j + 1..=j + below {
+                let l = self.get(i, j) / pivot;
+                *self.get_mut(i, j) = l;
+                for k in j + 1..=last {
+                    let u = self.get(j, k);
+                    *self.get_mut(i, k) = self.get(i, k) - l * u;
+                }
+            }
+        }
+        true
+    }
+
+    fn solve_mut(&self, mut b: DVectorViewMut<F>) {
+        let n = self.n;
+        for j in 0..n {
+            b.swap_rows(j, self.pivots[j]);
+            for i in j + 1..=j + self.lower.min(n - 1 - j) {
+                b[i] = b[i] - self.get(i, j) * b[j];
+            }
+        }
+        for j in (0..n).rev() {
+            b[j] = b[j] / self.get(j, j);
+            for i in j.saturating_sub(self.lower + self.upper)..j {
+                b[i] = b[i] - self.get(i, j) * b[j];
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+    use crate::{
+        SystemJacobian,
+        jacobian::JacobianStrategy,
+        linear_solver::{
+            DenseSolver,
+            tests::{Linear, solve},
+        },
+    };
+
+    #[test]
+    fn matches_dense_with_row_swaps() {
+        let (n, lower, upper) = (12, 2, 1);
+        // A diagonal too small to pivot on, so that rows are swapped and fill
+        // in past the upper band
+        let a = DMatrix::from_fn(n, n, |i, j| {
+            if i == j {
+                1e-3
+            } else if i <= j + lower && j <= i + upper {
+                ((5 * i + 3 * j) as f64).sin() + 2.
+            } else {
+                0.
+            }
+        });
+        let b = DVector::from_fn(n, |i, _| (i as f64).cos());
+        for shift in [0., 0.5] {
+            let expected = solve(&DenseSolver::new(), &a, shift, &b).unwrap();
+            let x = solve(&BandedSolver::new(lower, upper), &a, shift, &b).unwrap();
+            assert_relative_eq!(x, expected, max_relative = 1e-10);
+        }
+        let solver = BandedSolver::new(lower, upper);
+        let sys = Linear(a);
+        let mut cache = LinearSolver::<f64>::init_cache(&solver, &sys);
+        let jacobian = SystemJacobian;
+        let mut jacobian_cache = jacobian.init_cache(&sys);
+        let y = DVector::zeros(n);
+        solver.update_jacobian(
+            &mut cache,
+            &jacobian,
+            &mut jacobian_cache,
+            &sys,
+            y.as_view(),
+            0.,
+        );
+        assert!(solver.factor(&mut cache, &sys, 0.));
+        assert!(cache.lu.pivots.iter().enumerate().any(|(j, &p)| p != j));
+    }
+
+    #[test]
+    fn singular() {
+        // The first column is zero
+        let a = DMatrix::from_fn(
+            5,
+            5,
+            |i, j| {
+                if j > 0 && i.abs_diff(j) <= 1 { 1. } else { 0. }
+            },
+        );
+        let b = DVector::from_element(5, 1.);
+        assert!(solve(&BandedSolver::new(1, 1), &a, 0., &b).is_none());
+    }
+}
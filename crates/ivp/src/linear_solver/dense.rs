@@ -0,0 +1,125 @@
+use nalgebra::*;
+use num_traits::Float;
+
+use super::LinearSolver;
+use crate::{jacobian::JacobianOf, system::OdeSystem};
+
+/// LU with partial pivoting of a dense `W`, factorized in place so that
+/// refactorizing doesn't allocate
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DenseSolver;
+
+impl DenseSolver {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+pub struct DenseSolverCache<F: ComplexField> {
+    jacobian: DMatrix<F>,
+    mass_matrix: DMatrix<F>,
+    /// `L` below the diagonal and `U` on and above it
+    lu: DMatrix<F>,
+    /// The row swapped with each row during the factorization, in order
+    pivots: Vec<usize>,
+    singular: bool,
+}
+
+impl<F: Float + ComplexField<RealField = F>> LinearSolver<F> for DenseSolver {
+    type Cache = DenseSolverCache<F>;
+
+    fn init_cache<S: OdeSystem<F>>(&self, sys: &S) -> Self::Cache {
+        let n = sys.dimension();
+        DenseSolverCache {
+            jacobian: DMatrix::zeros(n, n),
+            mass_matrix: sys.mass_matrix(),
+            lu: DMatrix::zeros(n, n),
+            pivots: vec![0; n],
+            singular: true,
+        }
+    }
+
+    fn update_jacobian<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+        &self,
+        cache: &mut Self::Cache,
+        jacobian: &J,
+        jacobian_cache: &mut J::Cache,
+        sys: &S,
+        y: DVectorView<F>,
+        t: F,
+    ) {
+        jacobian.jacobian(jacobian_cache, sys, cache.jacobian.as_view_mut(), y, t);
+    }
+
+    fn factor<S: OdeSystem<F>>(&self, cache: &mut Self::Cache, _sys: &S, shift: F) -> bool {
+        let lu = &mut cache.lu;
+        lu.copy_from(&cache.jacobian);
+        lu.zip_apply(&cache.mass_matrix, |w, m| *w -= shift * m);
+        cache.singular = !factor_mut(lu, &mut cache.pivots);
+        !cache.singular
+    }
+
+    fn solve<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+        &self,
+        cache: &mut Self::Cache,
+        _jacobian: &J,
+        _jacobian_cache: &mut J::Cache,
+        _sys: &S,
+        mut b: DVectorViewMut<F>,
+    ) -> bool {
+        if cache.singular {
+            return false;
+        }
+        let (lu, n) = (&cache.lu, cache.lu.nrows());
+        for (j, &p) in cache.pivots.iter().enumerate() {
+            b.swap_rows(j, p);
+        }
+        for j in 0..n {
+            for i in j + 1..n {
+                b[i] = b[i] - lu[(i, j)] * b[j];
+            }
+        }
+        for j in (0..n).rev() {
+            b[j] /= lu[(j, j)];
+            for i in 0..j {
+                b[i] = b[i] - lu[(i, j)] * b[j];
+            }
+        }
+        true
+    }
+
+    fn mass_mul(&self, cache: &Self::Cache, x: DVectorView<F>, mut out: DVectorViewMut<F>) {
+        out.gemv(F::one(), &cache.mass_matrix, &x, F::zero());
+    }
+}
+
+/// Overwrite `a` with its LU factorization with partial pivoting, returning
+/// false if it is singular
+fn factor_mut<F: Scalar + Float>(a: &mut DMatrix<F>, pivots: &mut [usize]) -> bool {
+    let n = a.nrows();
+    for j in 0..n {
+        let mut p = j;
+        for i in j + 1..n {
+            if Float::abs(a[(i, j)]) > Float::abs(a[(p, j)]) {
+                p = i;
+            }
+        }
+        pivots[j] = p;
+        let pivot = a[(p, j)];
+        if pivot == F::zero() || !pivot.is_finite() {
+            return false;
+        }
+        a.swap_rows(j, p);
+        for i in j + 1..n {
+            a[(i, j)] = a[(i, j)] / pivot;
+        }
+        for k in j + 1..n {
+            let u = a[(j, k)];
+            for i in j + 1..n {
+                a[(i, k)] = a[(i, k)] - a[(i, j)] * u;
+            }
+        }
+    }
+    true
+}
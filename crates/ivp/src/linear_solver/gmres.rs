@@ -0,0 +1,336 @@
+use nalgebra::*;
+use num_traits::Float;
+
+use super::{IdentityPreconditioner, LinearSolver, Preconditioner};
+use crate::{jacobian::JacobianOf, sparse::SparseMatrix, system::OdeSystem};
+
+/// Restarted GMRES with right preconditioning, which never forms `W` and only
+/// needs products of the Jacobian with vectors, see [`JacobianOf::jvp`].
+///
+/// Converges once the residual is below `rtol` times the norm of the right
+/// hand side, starting from zero. See Saad, "Iterative Methods for Sparse
+/// Linear Systems", section 6.5.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Gmres<P = IdentityPreconditioner> {
+    restart: usize,
+    max_iters: usize,
+    rtol: f64,
+    preconditioner: P,
+}
+
+impl Gmres {
+    pub fn new() -> Self {
+        Self {
+            restart: 30,
+            max_iters: 300,
+            rtol: 1e-8,
+            preconditioner: IdentityPreconditioner,
+        }
+    }
+}
+
+impl Default for Gmres {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P> Gmres<P> {
+    /// The size of the Krylov subspace before restarting
+    pub fn with_restart(self, restart: usize) -> Self {
+        assert!(restart > 0, "restart must be positive");
+        Self { restart, ..self }
+    }
+
+    /// The number of products with `W` after which the solve fails
+    pub fn with_max_iters(self, max_iters: usize) -> Self {
+        Self { max_iters, ..self }
+    }
+
+    pub fn with_tolerance(self, rtol: f64) -> Self {
+        Self { rtol, ..self }
+    }
+
+    pub fn with_preconditioner<P2>(self, preconditioner: P2) -> Gmres<P2> {
+        Gmres {
+            restart: self.restart,
+            max_iters: self.max_iters,
+            rtol: self.rtol,
+            preconditioner,
+        }
+    }
+}
+
+pub struct GmresCache<F, C> {
+    /// Where the Jacobian is taken, and `vfield` there
+    y: DVector<F>,
+    fy: DVector<F>,
+    t: F,
+    shift: F,
+    mass_matrix: SparseMatrix<F>,
+    preconditioner: C,
+    /// The orthonormal basis of the Krylov subspace
+    basis: Vec<DVector<F>>,
+    /// The Hessenberg matrix of the Arnoldi process, reduced to upper
+    /// triangular by Givens rotations as it is built
+    h: DMatrix<F>,
+    rotations: Vec<(F, F)>,
+    g: DVector<F>,
+    x: DVector<F>,
+    r: DVector<F>,
+    z: DVector<F>,
+    w: DVector<F>,
+    mz: DVector<F>,
+}
+
+impl<F: Float + ComplexField<RealField = F>, C> GmresCache<F, C> {
+    /// Set `w` to `W z`
+    fn w_mul<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+        &mut self,
+        jacobian: &J,
+        jacobian_cache: &mut J::Cache,
+        sys: &S,
+    ) {
+        let (y, fy, z) = (self.y.as_view(), self.fy.as_view(), self.z.as_view());
+        jacobian.jvp(jacobian_cache, sys, self.w.as_view_mut(), y, fy, self.t, z);
+        self.mass_matrix.mul_to(z, self.mz.as_view_mut());
+        self.w.axpy(-self.shift, &self.mz, F::one());
+    }
+
+    /// Overwrite `z` with `P⁻¹ z`
+    fn precondition<P, S, J>(
+        &mut self,
+        preconditioner: &P,
+        jacobian: &J,
+        jacobian_cache: &mut J::Cache,
+        sys: &S,
+    ) -> bool
+    where
+        P: Preconditioner<F, Cache = C>,
+        S: OdeSystem<F>,
+        J: JacobianOf<F, S>,
+    {
+        preconditioner.apply(
+            &mut self.preconditioner,
+            jacobian,
+            jacobian_cache,
+            sys,
+            self.z.as_view_mut(),
+        )
+    }
+}
+
+impl<F, P> LinearSolver<F> for Gmres<P>
+where
+    F: Float + ComplexField<RealField = F>,
+    P: Preconditioner<F>,
+{
+    type Cache = GmresCache<F, P::Cache>;
+
+    fn init_cache<S: OdeSystem<F>>(&self, sys: &S) -> Self::Cache {
+        let n = sys.dimension();
+        let m = self.restart;
+        GmresCache {
+            y: DVector::zeros(n),
+            fy: DVector::zeros(n),
+            t: F::zero(),
+            shift: F::zero(),
+            mass_matrix: sys.sparse_mass_matrix(),
+            preconditioner: self.preconditioner.init_cache(sys),
+            basis: vec![DVector::zeros(n); m + 1],
+            h: DMatrix::zeros(m + 1, m),
+            rotations: vec![(F::one(), F::zero()); m],
+            g: DVector::zeros(m + 1),
+            x: DVector::zeros(n),
+            r: DVector::zeros(n),
+            z: DVector::zeros(n),
+            w: DVector::zeros(n),
+            mz: DVector::zeros(n),
+        }
+    }
+
+    fn update_jacobian<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+        &self,
+        cache: &mut Self::Cache,
+        jacobian: &J,
+        jacobian_cache: &mut J::Cache,
+        sys: &S,
+        y: DVectorView<F>,
+        t: F,
+    ) {
+        cache.y.copy_from(&y);
+        cache.t = t;
+        sys.vfield(cache.fy.as_view_mut(), y, t);
+        self.preconditioner.update_jacobian(
+            &mut cache.preconditioner,
+            jacobian,
+            jacobian_cache,
+            sys,
+            y,
+            t,
+        );
+    }
+
+    fn factor<S: OdeSystem<F>>(&self, cache: &mut Self::Cache, sys: &S, shift: F) -> bool {
+        cache.shift = shift;
+        self.preconditioner
+            .factor(&mut cache.preconditioner, sys, shift)
+    }
+
+    fn solve<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+        &self,
+        cache: &mut Self::Cache,
+        jacobian: &J,
+        jacobian_cache: &mut J::Cache,
+        sys: &S,
+        mut b: DVectorViewMut<F>,
+    ) -> bool {
+        let tol = F::from(self.rtol).unwrap() * b.norm();
+        let mut iters = 0;
+        cache.x.fill(F::zero());
+        cache.r.copy_from(&b);
+        loop {
+            let beta = cache.r.norm();
+            if beta <= tol {
+                break;
+            }
+            cache.basis[0].copy_from(&cache.r);
+            cache.basis[0].unscale_mut(beta);
+            cache.g.fill(F::zero());
+            cache.g[0] = beta;
+            let mut k = 0;
+            let mut converged = false;
+            while k < self.restart && iters < self.max_iters {
+                cache.z.copy_from(&cache.basis[k]);
+                if !cache.precondition(&self.preconditioner, jacobian, jacobian_cache, sys) {
+                    return false;
+                }
+                cache.w_mul(jacobian, jacobian_cache, sys);
+                // Arnoldi with modified Gram-Schmidt
+                for i in 0..=k {
+                    let hik = cache.w.dot(&cache.basis[i]);
+                    cache.h[(i, k)] = hik;
+                    cache.w.axpy(-hik, &cache.basis[i], F::one());
+                }
+                let hnext = cache.w.norm();
+                if hnext > F::zero() {
+                    cache.basis[k + 1].copy_from(&cache.w);
+                    cache.basis[k + 1].unscale_mut(hnext);
+                }
+                // Keep h upper triangular, and g the rotated residual
+                for i in 0..k {
+                    let (c, s) = cache.rotations[i];
+                    let (a, b) = (cache.h[(i, k)], cache.h[(i + 1, k)]);
+                    cache.h[(i, k)] = c * a + s * b;
+                    cache.h[(i + 1, k)] = c * b - s * a;
+                }
+                let hkk = cache.h[(k, k)];
+                let rho = Float::hypot(hkk, hnext);
+                // W is singular on the subspace, so the residual can't be
+                // minimized there
+                if rho == F::zero() {
+                    return false;
+                }
+                let (c, s) = (hkk / rho, hnext / rho);
+                cache.rotations[k] = (c, s);
+                cache.h[(k, k)] = rho;
+                cache.g[k + 1] = -s * cache.g[k];
+                cache.g[k] = c * cache.g[k];
+                k += 1;
+                iters += 1;
+                // A zero hnext means the subspace contains the solution
+                converged = Float::abs(cache.g[k]) <= tol || hnext == F::zero();
+                if converged {
+                    break;
+                }
+            }
+            // The combination of the basis that minimizes the residual
+            for i in (0..k).rev() {
+                cache.g[i] = cache.g[i] / cache.h[(i, i)];
+                for j in 0..i {
+                    cache.g[j] = cache.g[j] - cache.h[(j, i)] * cache.g[i];
+                }
+            }
+            cache.z.fill(F::zero());
+            for i in 0..k {
+                cache.z.axpy(cache.g[i], &cache.basis[i], F::one());
+            }
+            if !cache.precondition(&self.preconditioner, jacobian, jacobian_cache, sys) {
+                return false;
+            }
+            cache.x += &cache.z;
+            // Trust the rotated residual, since with a finite difference jvp
+            // the true one can't be computed more accurately than it
+            if converged {
+                break;
+            }
+            if iters >= self.max_iters {
+                return false;
+            }
+            // Restart from the true residual
+            cache.z.copy_from(&cache.x);
+            cache.w_mul(jacobian, jacobian_cache, sys);
+            cache.r.copy_from(&b);
+            cache.r -= &cache.w;
+        }
+        b.copy_from(&cache.x);
+        true
+    }
+
+    fn mass_mul(&self, cache: &Self::Cache, x: DVectorView<F>, out: DVectorViewMut<F>) {
+        cache.mass_matrix.mul_to(x, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+    use crate::linear_solver::{BandedSolver, DenseSolver, tests::solve};
+
+    #[test]
+    fn restarts() {
+        let n = 12;
+        let a = DMatrix::from_fn(n, n, |i, j| {
+            let x = ((i * j + 3 * i + 7 * j) as f64).sin();
+            if i == j { x + 4. } else { x }
+        });
+        let b = DVector::from_fn(n, |i, _| (i as f64).cos());
+        let expected = solve(&DenseSolver::new(), &a, 0.5, &b).unwrap();
+        let gmres = Gmres::new().with_restart(3).with_tolerance(1e-12);
+        // A few products of W aren't enough, so it has to restart
+        assert!(solve(&gmres.with_max_iters(3), &a, 0.5, &b).is_none());
+        let x = solve(&gmres, &a, 0.5, &b).unwrap();
+        assert_relative_eq!(x, expected, max_relative = 1e-9);
+    }
+
+    #[test]
+    fn preconditioned() {
+        let n = 12;
+        let a = DMatrix::from_fn(n, n, |i, j| match i.abs_diff(j) {
+            0 => 4.,
+            1 => ((i + j) as f64).sin(),
+            _ => 1e-2 * ((i * j) as f64).cos(),
+        });
+        let b = DVector::from_fn(n, |i, _| (i as f64).cos());
+        let expected = solve(&DenseSolver::new(), &a, 0.5, &b).unwrap();
+        let gmres = Gmres::new()
+            .with_tolerance(1e-12)
+            .with_preconditioner(BandedSolver::new(1, 1));
+        let x = solve(&gmres, &a, 0.5, &b).unwrap();
+        assert_relative_eq!(x, expected, max_relative = 1e-9);
+    }
+
+    #[test]
+    fn breakdown() {
+        // W = 0, so the first product is zero and there is nothing to rotate
+        let a = DMatrix::zeros(4, 4);
+        let b = DVector::from_element(4, 1.);
+        assert!(solve(&Gmres::new(), &a, 0., &b).is_none());
+        // W = I, where the first product spans the solution
+        let x = solve(&Gmres::new(), &a, -1., &b).unwrap();
+        assert_relative_eq!(x, b);
+    }
+}
@@ -0,0 +1,275 @@
+use nalgebra::*;
+use num_traits::Float;
+
+use crate::{jacobian::JacobianOf, system::OdeSystem};
+
+pub mod auto;
+pub mod banded;
+pub mod dense;
+pub mod gmres;
+pub mod reuse;
+pub mod sparse;
+
+pub use auto::*;
+pub use banded::*;
+pub use dense::*;
+pub use gmres::*;
+pub use reuse::*;
+pub use sparse::*;
+
+/// Solves the linear systems `W x = b` of implicit and Rosenbrock methods,
+/// where `W = J - shift M` for the Jacobian `J` of the system and its mass
+/// matrix `M`.
+///
+/// The Jacobian and the factorization of `W` are separate steps so that a step
+/// algorithm can keep a Jacobian while `shift` changes with the step size.
+/// `jacobian_cache` is the [`JacobianStrategy::Cache`] of `jacobian`, which
+/// the step algorithm owns.
+///
+/// [`JacobianStrategy::Cache`]: crate::JacobianStrategy::Cache
+pub trait LinearSolver<F: Scalar + Float> {
+    type Cache;
+
+    fn init_cache<S: OdeSystem<F>>(&self, sys: &S) -> Self::Cache;
+
+    /// Evaluate the Jacobian at `(y, t)` and keep it for the following calls
+    /// to `factor`
+    fn update_jacobian<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+        &self,
+        cache: &mut Self::Cache,
+        jacobian: &J,
+        jacobian_cache: &mut J::Cache,
+        sys: &S,
+        y: DVectorView<F>,
+        t: F,
+    );
+
+    /// Prepare to solve with `W = J - shift M` for the kept Jacobian, returning
+    /// false if `W` is singular
+    fn factor<S: OdeSystem<F>>(&self, cache: &mut Self::Cache, sys: &S, shift: F) -> bool;
+
+    /// Overwrite `b` with the solution of `W x = b`, returning false if it
+    /// couldn't be found
+    fn solve<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+        &self,
+        cache: &mut Self::Cache,
+        jacobian: &J,
+        jacobian_cache: &mut J::Cache,
+        sys: &S,
+        b: DVectorViewMut<F>,
+    ) -> bool;
+
+    /// Set `out` to `M x`, with `M` stored however suits the solver
+    fn mass_mul(&self, cache: &Self::Cache, x: DVectorView<F>, out: DVectorViewMut<F>);
+}
+
+/// An approximation `P` of `W` for [`Gmres`], which converges quickly when
+/// `P⁻¹ W` is close to the identity.
+///
+/// The direct solvers are preconditioners too, so that e.g. a [`BandedSolver`]
+/// for the nearby couplings can precondition GMRES on a system with a few
+/// long range ones.
+pub trait Preconditioner<F: Scalar + Float> {
+    type Cache;
+
+    fn init_cache<S: OdeSystem<F>>(&self, sys: &S) -> Self::Cache;
+
+    /// Called whenever the Jacobian is updated, at `(y, t)`
+    fn update_jacobian<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+        &self,
+        cache: &mut Self::Cache,
+        jacobian: &J,
+        jacobian_cache: &mut J::Cache,
+        sys: &S,
+        y: DVectorView<F>,
+        t: F,
+    );
+
+    /// Called whenever `W = J - shift M` changes, returning false if `P` is
+    /// singular
+    fn factor<S: OdeSystem<F>>(&self, cache: &mut Self::Cache, sys: &S, shift: F) -> bool;
+
+    /// Overwrite `r` with `P⁻¹ r`
+    fn apply<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+        &self,
+        cache: &mut Self::Cache,
+        jacobian: &J,
+        jacobian_cache: &mut J::Cache,
+        sys: &S,
+        r: DVectorViewMut<F>,
+    ) -> bool;
+}
+
+/// No preconditioning, `P = I`
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IdentityPreconditioner;
+
+impl<F: Scalar + Float> Preconditioner<F> for IdentityPreconditioner {
+    type Cache = ();
+
+    fn init_cache<S: OdeSystem<F>>(&self, _sys: &S) -> Self::Cache {}
+
+    fn update_jacobian<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+        &self,
+        _cache: &mut Self::Cache,
+        _jacobian: &J,
+        _jacobian_cache: &mut J::Cache,
+        _sys: &S,
+        _y: DVectorView<F>,
+        _t: F,
+    ) {
+    }
+
+    fn factor<S: OdeSystem<F>>(&self, _cache: &mut Self::Cache, _sys: &S, _shift: F) -> bool {
+        true
+    }
+
+    fn apply<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+        &self,
+        _cache: &mut Self::Cache,
+        _jacobian: &J,
+        _jacobian_cache: &mut J::Cache,
+        _sys: &S,
+        _r: DVectorViewMut<F>,
+    ) -> bool {
+        true
+    }
+}
+
+// A blanket impl for every `LinearSolver` would overlap with the one above
+macro_rules! impl_preconditioner_for_solver {
+    ($($solver:ty),*) => {$(
+        impl<F: Float + ComplexField<RealField = F>> Preconditioner<F> for $solver {
+            type Cache = <$solver as LinearSolver<F>>::Cache;
+
+            fn init_cache<S: OdeSystem<F>>(&self, sys: &S) -> Self::Cache {
+                LinearSolver::init_cache(self, sys)
+            }
+
+            fn update_jacobian<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+                &self,
+                cache: &mut Self::Cache,
+                jacobian: &J,
+                jacobian_cache: &mut J::Cache,
+                sys: &S,
+                y: DVectorView<F>,
+                t: F,
+            ) {
+                LinearSolver::update_jacobian(self, cache, jacobian, jacobian_cache, sys, y, t);
+            }
+
+            fn factor<S: OdeSystem<F>>(&self, cache: &mut Self::Cache, sys: &S, shift: F) -> bool {
+                LinearSolver::factor(self, cache, sys, shift)
+            }
+
+            fn apply<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+                &self,
+                cache: &mut Self::Cache,
+                jacobian: &J,
+                jacobian_cache: &mut J::Cache,
+                sys: &S,
+                r: DVectorViewMut<F>,
+            ) -> bool {
+                self.solve(cache, jacobian, jacobian_cache, sys, r)
+            }
+        }
+    )*};
+}
+
+impl_preconditioner_for_solver!(DenseSolver, BandedSolver, SparseSolver, AutoSolver);
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{
+        jacobian::{FiniteDifferenceCache, JacobianStrategy, SystemJacobian},
+        sparse::{ColumnColoring, SparseMatrix},
+    };
+
+    /// `y' = A y`, whose Jacobian is `A` exactly
+    pub(crate) struct Linear(pub(crate) DMatrix<f64>);
+
+    impl OdeSystem<f64> for Linear {
+        fn dimension(&self) -> usize {
+            self.0.nrows()
+        }
+
+        fn labels(&self) -> Vec<String> {
+            (0..self.dimension()).map(|i| format!("y{i}")).collect()
+        }
+
+        fn vfield(&self, mut out: DVectorViewMut<f64>, y: DVectorView<f64>, _t: f64) {
+            out.gemv(1., &self.0, &y, 0.);
+        }
+
+        fn jacobian(
+            &self,
+            mut out: DMatrixViewMut<f64>,
+            _y: DVectorView<f64>,
+            _t: f64,
+            _scratch: &mut FiniteDifferenceCache<f64>,
+        ) {
+            out.copy_from(&self.0);
+        }
+
+        fn sparse_jacobian(
+            &self,
+            out: &mut SparseMatrix<f64>,
+            _y: DVectorView<f64>,
+            _t: f64,
+            _coloring: &ColumnColoring,
+            _scratch: &mut FiniteDifferenceCache<f64>,
+        ) {
+            *out = SparseMatrix::from_dense(out.pattern().clone(), self.0.as_view());
+        }
+
+        fn jvp(
+            &self,
+            mut out: DVectorViewMut<f64>,
+            _y: DVectorView<f64>,
+            _fy: DVectorView<f64>,
+            _t: f64,
+            v: DVectorView<f64>,
+            _scratch: &mut FiniteDifferenceCache<f64>,
+        ) {
+            out.gemv(1., &self.0, &v, 0.);
+        }
+    }
+
+    /// The solution of `(A - shift I) x = b` with `solver`, or `None` if it
+    /// fails
+    pub(crate) fn solve<L: LinearSolver<f64>>(
+        solver: &L,
+        a: &DMatrix<f64>,
+        shift: f64,
+        b: &DVector<f64>,
+    ) -> Option<DVector<f64>> {
+        let sys = Linear(a.clone());
+        let jacobian = SystemJacobian;
+        let mut jacobian_cache = jacobian.init_cache(&sys);
+        let mut cache = solver.init_cache(&sys);
+        let y = DVector::zeros(a.nrows());
+        solver.update_jacobian(
+            &mut cache,
+            &jacobian,
+            &mut jacobian_cache,
+            &sys,
+            y.as_view(),
+            0.,
+        );
+        if !solver.factor(&mut cache, &sys, shift) {
+            return None;
+        }
+        let mut x = b.clone();
+        solver
+            .solve(
+                &mut cache,
+                &jacobian,
+                &mut jacobian_cache,
+                &sys,
+                x.as_view_mut(),
+            )
+            .then_some(x)
+    }
+}
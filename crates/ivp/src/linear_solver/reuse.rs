@@ -0,0 +1,132 @@
+use num_traits::Float;
+
+/// When a step algorithm recomputes the Jacobian rather than keeping the one
+/// from an earlier step, which is worth it when the Jacobian costs more than
+/// the rest of a step.
+///
+/// A kept Jacobian is always recomputed after a rejected step, a failed
+/// Newton iteration or a singular `W`, since each of those can mean that it is
+/// out of date.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JacobianReuse {
+    max_age: usize,
+    max_dt_ratio: f64,
+}
+
+impl JacobianReuse {
+    /// Recompute the Jacobian at every step, keeping it only to retry a
+    /// rejected step from the same point
+    pub fn never() -> Self {
+        Self {
+            max_age: 0,
+            max_dt_ratio: 1.,
+        }
+    }
+
+    /// Keep the Jacobian for up to `max_age` further steps, as long as dt stays
+    /// within a factor of `max_dt_ratio` of the dt it was computed with
+    pub fn new(max_age: usize, max_dt_ratio: f64) -> Self {
+        assert!(max_dt_ratio >= 1., "max_dt_ratio must be at least 1");
+        Self {
+            max_age,
+            max_dt_ratio,
+        }
+    }
+}
+
+impl Default for JacobianReuse {
+    fn default() -> Self {
+        Self::never()
+    }
+}
+
+/// What a step algorithm needs to remember to follow a [`JacobianReuse`]
+#[derive(Clone, Debug)]
+pub struct ReuseState<F> {
+    /// The time and dt that the kept Jacobian was computed at, if any
+    jacobian: Option<(F, F)>,
+    /// The number of steps started since then
+    age: usize,
+    last_t: Option<F>,
+    /// The dt that `W` was last factorized with
+    factored_dt: Option<F>,
+    /// Set when the kept Jacobian is known to be out of date
+    stale: bool,
+}
+
+impl<F: Float> Default for ReuseState<F> {
+    fn default() -> Self {
+        Self {
+            jacobian: None,
+            age: 0,
+            last_t: None,
+            factored_dt: None,
+            stale: false,
+        }
+    }
+}
+
+impl<F: Float> ReuseState<F> {
+    /// Call at the start of every attempted step, to find out whether the
+    /// Jacobian has to be recomputed. If it does, `W` has to be refactorized
+    /// too.
+    pub fn needs_jacobian(&mut self, policy: &JacobianReuse, t: F, dt: F) -> bool {
+        if self.last_t != Some(t) {
+            self.last_t = Some(t);
+            self.age += 1;
+        }
+        let Some((jac_t, jac_dt)) = self.jacobian else {
+            return true;
+        };
+        if self.stale {
+            return true;
+        }
+        if jac_t == t {
+            return false;
+        }
+        let ratio = (dt / jac_dt).abs().to_f64().unwrap();
+        self.age > policy.max_age || ratio > policy.max_dt_ratio || ratio * policy.max_dt_ratio < 1.
+    }
+
+    pub fn jacobian_updated(&mut self, t: F, dt: F) {
+        self.jacobian = Some((t, dt));
+        self.age = 0;
+        self.stale = false;
+        self.factored_dt = None;
+    }
+
+    /// Whether `W` has to be refactorized for a step of `dt`
+    pub fn needs_factor(&self, dt: F) -> bool {
+        self.factored_dt != Some(dt)
+    }
+
+    pub fn factored(&mut self, dt: F) {
+        self.factored_dt = Some(dt);
+    }
+
+    /// Whether the kept Jacobian was computed at an earlier step
+    pub fn is_old(&self) -> bool {
+        self.age > 0
+    }
+
+    /// The step was rejected, so a Jacobian from an earlier step may be what
+    /// went wrong
+    pub fn step_rejected(&mut self) {
+        if self.is_old() {
+            self.stale = true;
+        }
+    }
+
+    /// The state was changed between steps, e.g. by a callback, so the kept
+    /// Jacobian may be of a different state
+    pub fn state_modified(&mut self) {
+        self.stale = true;
+    }
+
+    /// A Newton iteration diverged, or `W` turned out to be singular
+    pub fn convergence_failure(&mut self) {
+        self.stale = true;
+        self.factored_dt = None;
+    }
+}
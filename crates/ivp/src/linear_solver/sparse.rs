@@ -0,0 +1,115 @@
+use nalgebra::*;
+use num_traits::Float;
+
+use super::LinearSolver;
+use crate::{
+    jacobian::JacobianOf,
+    sparse::{ColumnColoring, SparseLU, SparseMatrix, SparsityPattern},
+    system::OdeSystem,
+};
+
+/// [`SparseLU`] of `W`, for systems that give a
+/// [`jacobian_sparsity`](OdeSystem::jacobian_sparsity)
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SparseSolver;
+
+impl SparseSolver {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+pub struct SparseSolverCache<F> {
+    /// The Jacobian, stored with the pattern of `W`
+    jacobian: SparseMatrix<F>,
+    coloring: ColumnColoring,
+    mass_matrix: SparseMatrix<F>,
+    w: SparseMatrix<F>,
+    /// Kept after the first factorization so that its storage is reused
+    lu: Option<SparseLU<F>>,
+    singular: bool,
+}
+
+impl<F: Float + ComplexField<RealField = F>> LinearSolver<F> for SparseSolver {
+    type Cache = SparseSolverCache<F>;
+
+    fn init_cache<S: OdeSystem<F>>(&self, sys: &S) -> Self::Cache {
+        let jacobian = sys
+            .jacobian_sparsity()
+            .expect("SparseSolver needs a system with a jacobian_sparsity");
+        let mass_matrix = sys.sparse_mass_matrix();
+        let pattern = jacobian
+            .union(mass_matrix.pattern())
+            .union(&SparsityPattern::identity(sys.dimension()));
+        SparseSolverCache {
+            coloring: ColumnColoring::new(&pattern),
+            jacobian: SparseMatrix::zeros(pattern.clone()),
+            w: SparseMatrix::zeros(pattern),
+            mass_matrix,
+            lu: None,
+            singular: true,
+        }
+    }
+
+    fn update_jacobian<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+        &self,
+        cache: &mut Self::Cache,
+        jacobian: &J,
+        jacobian_cache: &mut J::Cache,
+        sys: &S,
+        y: DVectorView<F>,
+        t: F,
+    ) {
+        cache.jacobian.values_mut().fill(F::zero());
+        jacobian.sparse_jacobian(
+            jacobian_cache,
+            sys,
+            &cache.coloring,
+            &mut cache.jacobian,
+            y,
+            t,
+        );
+    }
+
+    fn factor<S: OdeSystem<F>>(&self, cache: &mut Self::Cache, _sys: &S, shift: F) -> bool {
+        cache
+            .w
+            .values_mut()
+            .copy_from_slice(cache.jacobian.values());
+        let mass_matrix = &cache.mass_matrix;
+        for ((i, j), &m) in mass_matrix.pattern().entries().zip(mass_matrix.values()) {
+            cache.w.add_to(i, j, -shift * m);
+        }
+        let factored = match &mut cache.lu {
+            Some(lu) => lu.factor(&cache.w),
+            None => {
+                cache.lu = SparseLU::new(&cache.w);
+                cache.lu.is_some()
+            }
+        };
+        cache.singular = !factored;
+        factored
+    }
+
+    fn solve<S: OdeSystem<F>, J: JacobianOf<F, S>>(
+        &self,
+        cache: &mut Self::Cache,
+        _jacobian: &J,
+        _jacobian_cache: &mut J::Cache,
+        _sys: &S,
+        b: DVectorViewMut<F>,
+    ) -> bool {
+        match &mut cache.lu {
+            Some(lu) if !cache.singular => {
+                lu.solve_mut(b);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn mass_mul(&self, cache: &Self::Cache, x: DVectorView<F>, out: DVectorViewMut<F>) {
+        cache.mass_matrix.mul_to(x, out);
+    }
+}
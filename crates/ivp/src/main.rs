@@ -1,3 +1,2 @@
 #[allow(unused)]
-
 fn main() {}
@@ -4,11 +4,22 @@ use std::io::Write as _;
 use std::process::Command;
 use std::process::Stdio;
 
-use crate::{AdaptiveStrategy, Integrator, OdeSystem, StepAlgorithm, TSpan};
+use crate::{
+    AdaptiveStrategy, Integrator, JacobianOf, OdeSystem, SaveAt, SolveError, SolveOptions,
+    StepAlgorithm, TSpan,
+};
 use nalgebra::*;
 use num_traits::{Float, cast};
 use tempfile::NamedTempFile;
 
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "F: Scalar + serde::Serialize, S: serde::Serialize",
+        deserialize = "F: Scalar + serde::Deserialize<'de>, S: serde::Deserialize<'de>"
+    ))
+)]
 pub struct OdeProblem<F, S> {
     sys: S,
     y0: DVector<F>,
@@ -20,64 +31,164 @@ impl<F: Float + Scalar, S: OdeSystem<F>> OdeProblem<F, S> {
         Self { sys, y0, tspan }
     }
 
-    pub fn solve<'a, SA: StepAlgorithm<F>, AS: AdaptiveStrategy<F, SA::ErrorEstimate>>(
+    pub fn system(&self) -> &S {
+        &self.sys
+    }
+
+    pub fn y0(&self) -> &DVector<F> {
+        &self.y0
+    }
+
+    pub fn tspan(&self) -> TSpan<F> {
+        self.tspan
+    }
+
+    pub fn solve<SA, AS>(
+        &self,
+        step_algorithm: &SA,
+        adaptive_strategy: &AS,
+    ) -> Result<OdeSolution<F, SA>, SolveError<F>>
+    where
+        SA: StepAlgorithm<F> + Clone,
+        SA::Jacobian: JacobianOf<F, S>,
+        AS: AdaptiveStrategy<F, SA::ErrorEstimate>,
+    {
+        self.solve_with_options(step_algorithm, adaptive_strategy, SolveOptions::default())
+    }
+
+    /// An integrator at the start of the time span, for stepping through the
+    /// solve by hand
+    pub fn init<'a, SA, AS>(
         &'a self,
         step_algorithm: &'a SA,
         adaptive_strategy: &'a AS,
-    ) -> OdeSolution<'a, F, SA> {
-        let mut integrator = Integrator::new(
+        opts: SolveOptions<F>,
+    ) -> Integrator<'a, F, S, SA, AS>
+    where
+        SA: StepAlgorithm<F>,
+        SA::Jacobian: JacobianOf<F, S>,
+        AS: AdaptiveStrategy<F, SA::ErrorEstimate>,
+    {
+        Integrator::new(
             &self.sys,
             step_algorithm,
             adaptive_strategy,
             self.tspan,
             self.y0.clone(),
-        );
-        while *integrator.ts.last().unwrap() < self.tspan.end {
-            integrator.step();
-        }
-        OdeSolution {
-            labels: self.sys.labels(),
-            tspan: self.tspan,
-            ts: integrator.ts,
-            ys: integrator.ys,
-            step_algorithm: integrator.step_algorithm,
-            interpolants: integrator.interpolants,
-        }
+            opts,
+        )
+    }
+
+    pub fn solve_with_options<SA, AS>(
+        &self,
+        step_algorithm: &SA,
+        adaptive_strategy: &AS,
+        opts: SolveOptions<F>,
+    ) -> Result<OdeSolution<F, SA>, SolveError<F>>
+    where
+        SA: StepAlgorithm<F> + Clone,
+        SA::Jacobian: JacobianOf<F, S>,
+        AS: AdaptiveStrategy<F, SA::ErrorEstimate>,
+    {
+        self.init(step_algorithm, adaptive_strategy, opts).solve()
     }
 }
 
-pub struct OdeSolution<'a, F: Float + Scalar + 'static, SA: StepAlgorithm<F>> {
-    labels: Vec<String>,
-    tspan: TSpan<F>,
-    ts: Vec<F>,
-    ys: Vec<DVector<F>>,
-    step_algorithm: &'a SA,
-    interpolants: Vec<SA::Interpolant>,
+/// The result of a solve. It owns everything it needs to interpolate, including
+/// a copy of the step algorithm, so it can outlive the problem and is `Send`
+/// and `Sync` when `F` and the step algorithm are.
+///
+/// With the `serde` feature it can be stored and loaded again. The dense output
+/// of a loaded solution is only the same if the format reads floats back
+/// exactly, which for serde_json takes its `float_roundtrip` feature.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "F: serde::Serialize, SA: serde::Serialize, SA::Interpolant: serde::Serialize",
+        deserialize = "F: serde::Deserialize<'de>, SA: serde::Deserialize<'de>, \
+                       SA::Interpolant: serde::Deserialize<'de>"
+    ))
+)]
+pub struct OdeSolution<F: Float + Scalar + 'static, SA: StepAlgorithm<F>> {
+    pub(crate) labels: Vec<String>,
+    pub(crate) tspan: TSpan<F>,
+    pub(crate) ts: Vec<F>,
+    pub(crate) ys: Vec<DVector<F>>,
+    pub(crate) step_algorithm: SA,
+    pub(crate) interpolants: Vec<SA::Interpolant>,
+    pub(crate) saveat: SaveAt<F>,
+    pub(crate) save_idxs: Option<Vec<usize>>,
+    pub(crate) dense: bool,
 }
 
-impl<'a, F: Float + Scalar, SA: StepAlgorithm<F>> OdeSolution<'a, F, SA> {
+impl<F: Float + Scalar, SA: StepAlgorithm<F>> OdeSolution<F, SA> {
+    /// The times of the saved states
+    pub fn ts(&self) -> &[F] {
+        &self.ts
+    }
+
+    /// The saved states, of the components in [`save_idxs`](Self::save_idxs)
+    pub fn ys(&self) -> &[DVector<F>] {
+        &self.ys
+    }
+
+    /// Which states were saved, see [`SolveOptions::saveat`]
+    pub fn saveat(&self) -> &SaveAt<F> {
+        &self.saveat
+    }
+
+    /// The components that were saved, or `None` for all of them
+    pub fn save_idxs(&self) -> Option<&[usize]> {
+        self.save_idxs.as_deref()
+    }
+
+    /// Whether the interpolants of the steps were kept, see
+    /// [`SolveOptions::dense`]
+    pub fn is_dense(&self) -> bool {
+        self.dense
+    }
+
+    /// The saved components at `t`, from the dense output if there is one and
+    /// interpolating linearly between the saved states otherwise. Outside of
+    /// the saved times this is the first or last saved state.
     pub fn solution_at(&self, t: F) -> DVector<F> {
-        if t <= self.tspan.start {
+        assert!(!self.ts.is_empty(), "no states were saved");
+        let tdir = self.tspan.direction();
+        if tdir * t <= tdir * self.ts[0] {
             return self.ys[0].clone();
-        } else if t >= self.tspan.end {
+        } else if tdir * t >= tdir * *self.ts.last().unwrap() {
             return self.ys.last().unwrap().clone();
         }
-        let k = self.ts.partition_point(|ti| *ti < t).min(self.ts.len() - 1);
-        let y0 = self.ys[k - 1].as_view();
-        let y1 = self.ys[k].as_view();
+        let k = self
+            .ts
+            .partition_point(|ti| tdir * *ti < tdir * t)
+            .min(self.ts.len() - 1);
+        let (y0, y1) = (&self.ys[k - 1], &self.ys[k]);
         let t0 = self.ts[k - 1];
         let t1 = self.ts[k];
         let dt = t1 - t0;
-        self.step_algorithm
-            .interpolate(y0, y1, &self.interpolants[k - 1], dt, (t - t0) / dt)
+        let s = (t - t0) / dt;
+        if !self.dense {
+            return y0.zip_map(y1, |a, b| (F::one() - s) * a + s * b);
+        }
+        self.step_algorithm.interpolate(
+            y0.as_view(),
+            y1.as_view(),
+            &self.interpolants[k - 1],
+            dt,
+            s,
+        )
     }
 
     pub fn gnuplot(&self, dt: f32)
     where
         F: Display + Debug,
     {
+        let tdir = self.tspan.direction();
         let n: usize =
-            cast(((self.tspan.end - self.tspan.start) / F::from(dt).unwrap()).ceil()).unwrap();
+            cast(((self.tspan.end - self.tspan.start).abs() / F::from(dt).unwrap()).ceil())
+                .unwrap();
         let mut tmp = NamedTempFile::new().unwrap();
         let mut script = String::new();
         write!(&mut script, "plot ").unwrap();
@@ -95,7 +206,7 @@ impl<'a, F: Float + Scalar, SA: StepAlgorithm<F>> OdeSolution<'a, F, SA> {
             .unwrap();
         }
         for k in 0..n {
-            let t = F::from(k as f32 * dt).unwrap();
+            let t = self.tspan.start + tdir * F::from(k as f32 * dt).unwrap();
             write!(&mut tmp, "{t}").unwrap();
             let y = self.solution_at(t);
             for i in 0..self.labels.len() {
@@ -130,9 +241,10 @@ impl<'a, F: Float + Scalar, SA: StepAlgorithm<F>> OdeSolution<'a, F, SA> {
             .unwrap();
         }
         writeln!(&mut script_tmp, "unset multiplot").unwrap();
+        let tdir = self.tspan.direction();
         let mut t = self.tspan.start;
-        let factor = F::from(10.).unwrap().powf(dt);
-        while t < self.tspan.end {
+        let factor = F::from(10.).unwrap().powf(tdir * dt);
+        while tdir * t < tdir * self.tspan.end {
             write!(&mut tmp, "{}", t.log10()).unwrap();
             // print!("{}", t.log10());
             let y = self.solution_at(t);
@@ -153,3 +265,38 @@ impl<'a, F: Float + Scalar, SA: StepAlgorithm<F>> OdeSolution<'a, F, SA> {
         process.wait().expect("failed to plot");
     }
 }
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use crate::{IntegralController, Rosenbrock23, Tsit5, linear_solver::tests::Linear};
+
+    /// Solve `y'' = -y`, store the solution as JSON, load it again and compare
+    /// the two halfway between the saved states, where the dense output is used
+    fn assert_round_trip<SA>(step_algorithm: &SA)
+    where
+        SA: StepAlgorithm<f64, ErrorEstimate = DVector<f64>> + Clone,
+        SA: serde::Serialize + serde::de::DeserializeOwned,
+        SA::Jacobian: JacobianOf<f64, Linear>,
+        SA::Interpolant: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        let sys = Linear(DMatrix::from_row_slice(2, 2, &[0., 1., -1., 0.]));
+        let problem = OdeProblem::new(sys, dvector![1., 0.], TSpan::new(0., 5.));
+        let controller = IntegralController::new(1e-6, 1e-6, 4);
+        let solution = problem.solve(step_algorithm, &controller).unwrap();
+        let json = serde_json::to_string(&solution).unwrap();
+        let loaded: OdeSolution<f64, SA> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.ts(), solution.ts());
+        assert!(loaded.is_dense());
+        for w in solution.ts().windows(2) {
+            let t = (w[0] + w[1]) / 2.;
+            assert_eq!(loaded.solution_at(t), solution.solution_at(t));
+        }
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        assert_round_trip(&Tsit5);
+        assert_round_trip(&Rosenbrock23::new());
+    }
+}
@@ -0,0 +1,101 @@
+use super::SparsityPattern;
+
+/// A partition of the columns of a sparse matrix into groups that share no
+/// rows, so that all the columns in a group can be probed with a single
+/// evaluation of the vector field
+#[derive(Clone, Debug)]
+pub struct ColumnColoring {
+    colors: Vec<usize>,
+    groups: Vec<Vec<usize>>,
+}
+
+impl ColumnColoring {
+    /// Greedy coloring of the column intersection graph, visiting columns in
+    /// order. For a banded pattern this uses as many colors as the bandwidth.
+    pub fn new(pattern: &SparsityPattern) -> Self {
+        let ncols = pattern.ncols();
+        // The columns with a nonzero in each row
+        let mut rows = vec![vec![]; pattern.nrows()];
+        for (i, j) in pattern.entries() {
+            rows[i].push(j);
+        }
+        let mut colors = vec![usize::MAX; ncols];
+        let mut groups: Vec<Vec<usize>> = vec![];
+        // forbidden[c] == j if color c is taken by a neighbour of column j
+        let mut forbidden = vec![usize::MAX; ncols];
+        for j in 0..ncols {
+            for &i in pattern.column(j) {
+                for &k in &rows[i] {
+                    if colors[k] != usize::MAX {
+                        forbidden[colors[k]] = j;
+                    }
+                }
+            }
+            let color = (0..groups.len())
+                .find(|&c| forbidden[c] != j)
+                .unwrap_or_else(|| {
+                    groups.push(vec![]);
+                    groups.len() - 1
+                });
+            colors[j] = color;
+            groups[color].push(j);
+        }
+        Self { colors, groups }
+    }
+
+    pub fn ncolors(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// The color of each column
+    pub fn colors(&self) -> &[usize] {
+        &self.colors
+    }
+
+    /// The columns of each color
+    pub fn groups(&self) -> &[Vec<usize>] {
+        &self.groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every column has exactly one color, and no two columns of a color share
+    /// a row
+    fn assert_valid(pattern: &SparsityPattern, coloring: &ColumnColoring) {
+        for (c, group) in coloring.groups().iter().enumerate() {
+            let mut rows = vec![false; pattern.nrows()];
+            for &j in group {
+                assert_eq!(coloring.colors()[j], c);
+                for &i in pattern.column(j) {
+                    assert!(!rows[i], "columns of color {c} share row {i}");
+                    rows[i] = true;
+                }
+            }
+        }
+        let colored: usize = coloring.groups().iter().map(Vec::len).sum();
+        assert_eq!(colored, pattern.ncols());
+    }
+
+    #[test]
+    fn banded() {
+        let pattern = SparsityPattern::banded(20, 2, 1);
+        let coloring = ColumnColoring::new(&pattern);
+        assert_valid(&pattern, &coloring);
+        assert_eq!(coloring.ncolors(), 4);
+    }
+
+    #[test]
+    fn arrow() {
+        // The dense first row and column conflict with everything, the rest of
+        // the diagonal with nothing
+        let n = 10;
+        let entries = (0..n).flat_map(|k| [(0, k), (k, 0), (k, k)]);
+        let pattern = SparsityPattern::from_entries(n, n, entries);
+        let coloring = ColumnColoring::new(&pattern);
+        assert_valid(&pattern, &coloring);
+        assert_eq!(coloring.ncolors(), n);
+    }
+}
@@ -0,0 +1,380 @@
+use nalgebra::*;
+use num_traits::Float;
+
+use super::SparseMatrix;
+
+/// `P A = L U` for a square sparse matrix, with threshold partial pivoting.
+///
+/// This is the left-looking algorithm of Gilbert and Peierls, as in `cs_lu`
+/// from Davis, "Direct Methods for Sparse Linear Systems". Columns are taken
+/// in their natural order, so there is no fill-reducing ordering; patterns
+/// from method-of-lines discretizations are usually banded, which is fine.
+///
+/// [`factor`](SparseLU::factor) reuses the storage of an earlier
+/// factorization, so refactorizing a matrix with the same pattern doesn't
+/// allocate.
+#[derive(Clone, Debug)]
+pub struct SparseLU<F> {
+    n: usize,
+    /// `pinv[i]` is the row of `L U` that row `i` of `A` ends up in
+    pinv: Vec<usize>,
+    /// Unit lower triangular, with the diagonal stored first in each column
+    l: CscFactor<F>,
+    /// Upper triangular, with the diagonal stored last in each column
+    u: CscFactor<F>,
+    /// Workspace for the factorization and the solves
+    x: Vec<F>,
+    reach: Reach,
+}
+
+#[derive(Clone, Debug)]
+struct CscFactor<F> {
+    col_ptrs: Vec<usize>,
+    row_indices: Vec<usize>,
+    values: Vec<F>,
+}
+
+impl<F> CscFactor<F> {
+    fn with_capacity(n: usize, nnz: usize) -> Self {
+        let mut col_ptrs = Vec::with_capacity(n + 1);
+        col_ptrs.push(0);
+        Self {
+            col_ptrs,
+            row_indices: Vec::with_capacity(nnz),
+            values: Vec::with_capacity(nnz),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.col_ptrs.truncate(1);
+        self.row_indices.clear();
+        self.values.clear();
+    }
+
+    fn push(&mut self, i: usize, x: F) {
+        self.row_indices.push(i);
+        self.values.push(x);
+    }
+
+    fn finish_column(&mut self) {
+        self.col_ptrs.push(self.row_indices.len());
+    }
+}
+
+/// How much smaller than the largest candidate the diagonal may be and still
+/// be preferred as the pivot, which avoids needless row swaps
+const PIVOT_TOLERANCE: f64 = 0.1;
+
+impl<F: Scalar + Float> SparseLU<F> {
+    /// Factorize `a`, returning `None` if it is singular
+    pub fn new(a: &SparseMatrix<F>) -> Option<Self> {
+        let pattern = a.pattern();
+        let n = pattern.ncols();
+        assert_eq!(pattern.nrows(), n, "LU needs a square matrix");
+        let mut lu = Self {
+            n,
+            pinv: vec![usize::MAX; n],
+            l: CscFactor::with_capacity(n, 4 * pattern.nnz() + n),
+            u: CscFactor::with_capacity(n, 4 * pattern.nnz() + n),
+            x: vec![F::zero(); n],
+            reach: Reach::new(n),
+        };
+        lu.factor(a).then_some(lu)
+    }
+
+    /// Replace this factorization with one of `a`, which must have the same
+    /// dimension, returning false if it is singular. Until a later call
+    /// succeeds, the factorization can't be used.
+    pub fn factor(&mut self, a: &SparseMatrix<F>) -> bool {
+        let pattern = a.pattern();
+        let n = self.n;
+        assert!(
+            pattern.nrows() == n && pattern.ncols() == n,
+            "refactorizing with a different dimension"
+        );
+        let tol = F::from(PIVOT_TOLERANCE).unwrap();
+        let Self {
+            pinv,
+            l,
+            u,
+            x,
+            reach,
+            ..
+        } = self;
+        l.clear();
+        u.clear();
+        pinv.fill(usize::MAX);
+        // A failed factorization leaves entries of x behind
+        x.fill(F::zero());
+        reach.reset();
+        for k in 0..n {
+            // Solve L x = A[:, k] for the rows reachable from the nonzeros of
+            // A[:, k] in the graph of L
+            let rows = pattern.column(k);
+            reach.compute(l, pinv, rows);
+            for (&i, &v) in rows.iter().zip(&a.values()[pattern.col_range(k)]) {
+                x[i] = v;
+            }
+            for &j in reach.nodes() {
+                let jj = pinv[j];
+                if jj == usize::MAX {
+                    continue;
+                }
+                let xj = x[j];
+                for p in l.col_ptrs[jj] + 1..l.col_ptrs[jj + 1] {
+                    let i = l.row_indices[p];
+                    x[i] = x[i] - l.values[p] * xj;
+                }
+            }
+            // Rows that already have a pivot go into U, the largest of the
+            // rest becomes the pivot
+            let mut ipiv = usize::MAX;
+            let mut largest = -F::one();
+            for &i in reach.nodes() {
+                if pinv[i] == usize::MAX {
+                    let t = x[i].abs();
+                    if t > largest {
+                        largest = t;
+                        ipiv = i;
+                    }
+                } else {
+                    u.push(pinv[i], x[i]);
+                }
+            }
+            if ipiv == usize::MAX || largest <= F::zero() || !largest.is_finite() {
+                return false;
+            }
+            if pinv[k] == usize::MAX && x[k].abs() >= largest * tol {
+                ipiv = k;
+            }
+            let pivot = x[ipiv];
+            u.push(k, pivot);
+            u.finish_column();
+            pinv[ipiv] = k;
+            l.push(ipiv, F::one());
+            for &i in reach.nodes() {
+                if pinv[i] == usize::MAX {
+                    l.push(i, x[i] / pivot);
+                }
+                x[i] = F::zero();
+            }
+            l.finish_column();
+        }
+        // Now that every row has a pivot, renumber the rows of L
+        for i in l.row_indices.iter_mut() {
+            *i = pinv[*i];
+        }
+        true
+    }
+
+    /// Overwrite `b` with the solution of `A x = b`
+    pub fn solve_mut(&mut self, mut b: DVectorViewMut<F>) {
+        let x = &mut self.x;
+        for i in 0..self.n {
+            x[self.pinv[i]] = b[i];
+        }
+        let l = &self.l;
+        for j in 0..self.n {
+            let xj = x[j];
+            for p in l.col_ptrs[j] + 1..l.col_ptrs[j + 1] {
+                x[l.row_indices[p]] = x[l.row_indices[p]] - l.values[p] * xj;
+            }
+        }
+        let u = &self.u;
+        for j in (0..self.n).rev() {
+            let diag = u.col_ptrs[j + 1] - 1;
+            x[j] = x[j] / u.values[diag];
+            let xj = x[j];
+            for p in u.col_ptrs[j]..diag {
+                x[u.row_indices[p]] = x[u.row_indices[p]] - u.values[p] * xj;
+            }
+        }
+        for i in 0..self.n {
+            b[i] = x[i];
+        }
+    }
+
+    /// The number of nonzeros in `L` and `U`, to see how much fill there was
+    pub fn nnz(&self) -> usize {
+        self.l.values.len() + self.u.values.len()
+    }
+}
+
+/// Depth first search through the graph of the partially built `L`, giving
+/// the nonzero pattern of `L \ b` in topological order
+#[derive(Clone, Debug)]
+struct Reach {
+    marked: Vec<bool>,
+    /// The result, filled from the back
+    out: Vec<usize>,
+    top: usize,
+    stack: Vec<(usize, usize)>,
+}
+
+impl Reach {
+    fn new(n: usize) -> Self {
+        Self {
+            marked: vec![false; n],
+            out: vec![0; n],
+            top: n,
+            stack: Vec::with_capacity(n),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.marked.fill(false);
+        self.top = self.out.len();
+        self.stack.clear();
+    }
+
+    fn nodes(&self) -> &[usize] {
+        &self.out[self.top..]
+    }
+
+    fn compute<F>(&mut self, l: &CscFactor<F>, pinv: &[usize], rows: &[usize]) {
+        for &i in &self.out[self.top..] {
+            self.marked[i] = false;
+        }
+        self.top = self.out.len();
+        for &start in rows {
+            if self.marked[start] {
+                continue;
+            }
+            self.marked[start] = true;
+            self.stack
+                .push((start, Self::children(l, pinv, start).start));
+            while let Some(&mut (j, ref mut next)) = self.stack.last_mut() {
+                let children = Self::children(l, pinv, j);
+                let unvisited = (*next..children.end).find(|&p| !self.marked[l.row_indices[p]]);
+                match unvisited {
+                    Some(p) => {
+                        *next = p + 1;
+                        let i = l.row_indices[p];
+                        self.marked[i] = true;
+                        self.stack.push((i, Self::children(l, pinv, i).start));
+                    }
+                    None => {
+                        self.stack.pop();
+                        self.top -= 1;
+                        self.out[self.top] = j;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The positions in `L` of the rows that row `j` of `A` updates, which is
+    /// nothing until `j` has been chosen as a pivot
+    fn children<F>(l: &CscFactor<F>, pinv: &[usize], j: usize) -> std::ops::Range<usize> {
+        match pinv[j] {
+            usize::MAX => 0..0,
+            jj => l.col_ptrs[jj] + 1..l.col_ptrs[jj + 1],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+
+    use super::*;
+    use crate::sparse::SparsityPattern;
+
+    /// Uniform in `[-1, 1)`, from a xorshift generator so that the tests are
+    /// reproducible
+    fn random(state: &mut u64) -> f64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        (*state >> 11) as f64 / (1u64 << 52) as f64 - 1.
+    }
+
+    /// A random matrix with a few nonzeros per column. The diagonal is too
+    /// small to pivot on, and a large entry below it keeps the matrix well
+    /// conditioned, so that the factorization has to swap rows.
+    fn random_matrix(n: usize, state: &mut u64) -> SparseMatrix<f64> {
+        let mut entries: Vec<_> = (0..n).flat_map(|j| [(j, j), ((j + 1) % n, j)]).collect();
+        for j in 0..n {
+            for _ in 0..3 {
+                let i = ((random(state) + 1.) / 2. * n as f64) as usize;
+                entries.push((i.min(n - 1), j));
+            }
+        }
+        let pattern = SparsityPattern::from_entries(n, n, entries);
+        let mut a = SparseMatrix::zeros(pattern);
+        refill(&mut a, state);
+        a
+    }
+
+    /// New random values in the same pattern
+    fn refill(a: &mut SparseMatrix<f64>, state: &mut u64) {
+        let n = a.pattern().ncols();
+        let entries: Vec<_> = a.pattern().entries().collect();
+        for ((i, j), x) in entries.into_iter().zip(a.values_mut()) {
+            *x = random(state);
+            if i == j {
+                *x *= 1e-3;
+            } else if i == (j + 1) % n {
+                *x += 3f64.copysign(*x);
+            }
+        }
+    }
+
+    fn assert_solves_like_dense(lu: &mut SparseLU<f64>, a: &SparseMatrix<f64>, state: &mut u64) {
+        let n = a.pattern().ncols();
+        let b = DVector::from_fn(n, |_, _| random(state));
+        let expected = a.to_dense().lu().solve(&b).unwrap();
+        let mut x = b.clone();
+        lu.solve_mut(x.as_view_mut());
+        assert_relative_eq!(x, expected, max_relative = 1e-9);
+        let mut ax = DVector::zeros(n);
+        a.mul_to(x.as_view(), ax.as_view_mut());
+        assert_relative_eq!(ax, b, epsilon = 1e-10);
+    }
+
+    #[test]
+    fn solves_with_pivoting() {
+        let mut state = 0x2545f4914f6cdd1d;
+        let a = random_matrix(40, &mut state);
+        let mut lu = SparseLU::new(&a).unwrap();
+        assert!(lu.pinv.iter().enumerate().any(|(i, &k)| i != k));
+        assert_solves_like_dense(&mut lu, &a, &mut state);
+    }
+
+    #[test]
+    fn refactorizes_the_same_pattern() {
+        let mut state = 0x9e3779b97f4a7c15;
+        let mut a = random_matrix(40, &mut state);
+        let mut lu = SparseLU::new(&a).unwrap();
+        for _ in 0..3 {
+            refill(&mut a, &mut state);
+            assert!(lu.factor(&a));
+            assert_solves_like_dense(&mut lu, &a, &mut state);
+        }
+    }
+
+    #[test]
+    fn detects_singular_matrices() {
+        // A column without nonzeros
+        let pattern = SparsityPattern::from_entries(3, 3, [(0, 0), (1, 0), (1, 1), (2, 1)]);
+        let a = SparseMatrix::from_dense(pattern, DMatrix::from_element(3, 3, 1.).as_view());
+        assert!(SparseLU::new(&a).is_none());
+        // The last column is the sum of the others, which cancels exactly
+        let a = dmatrix![
+            2., 1., 3.;
+            4., 0., 4.;
+            0., 8., 8.
+        ];
+        let singular = SparseMatrix::from_dense(SparsityPattern::banded(3, 2, 2), a.as_view());
+        assert!(SparseLU::new(&singular).is_none());
+        // A failed factorization can be followed by a good one
+        let mut state = 0x853c49e6748fea9b;
+        let mut a = random_matrix(3, &mut state);
+        let mut lu = SparseLU::new(&a).unwrap();
+        a.values_mut().fill(0.);
+        assert!(!lu.factor(&a));
+        refill(&mut a, &mut state);
+        assert!(lu.factor(&a));
+        assert_solves_like_dense(&mut lu, &a, &mut state);
+    }
+}
@@ -0,0 +1,200 @@
+use std::ops::Range;
+
+use nalgebra::*;
+use num_traits::Float;
+
+pub mod coloring;
+pub mod lu;
+
+pub use coloring::*;
+pub use lu::*;
+
+/// The positions of the structural nonzeros of a matrix, in compressed sparse
+/// column form with sorted row indices
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SparsityPattern {
+    nrows: usize,
+    ncols: usize,
+    col_ptrs: Vec<usize>,
+    row_indices: Vec<usize>,
+}
+
+impl SparsityPattern {
+    /// Build a pattern from `(row, column)` pairs, in any order and possibly
+    /// with duplicates
+    pub fn from_entries(
+        nrows: usize,
+        ncols: usize,
+        entries: impl IntoIterator<Item = (usize, usize)>,
+    ) -> Self {
+        let mut columns = vec![vec![]; ncols];
+        for (i, j) in entries {
+            assert!(
+                i < nrows && j < ncols,
+                "entry ({i}, {j}) is outside of a {nrows}x{ncols} matrix"
+            );
+            columns[j].push(i);
+        }
+        let mut col_ptrs = Vec::with_capacity(ncols + 1);
+        let mut row_indices = vec![];
+        col_ptrs.push(0);
+        for mut rows in columns {
+            rows.sort_unstable();
+            rows.dedup();
+            row_indices.extend(rows);
+            col_ptrs.push(row_indices.len());
+        }
+        Self {
+            nrows,
+            ncols,
+            col_ptrs,
+            row_indices,
+        }
+    }
+
+    pub fn identity(n: usize) -> Self {
+        Self::from_entries(n, n, (0..n).map(|i| (i, i)))
+    }
+
+    /// A square pattern with `lower` subdiagonals and `upper` superdiagonals
+    pub fn banded(n: usize, lower: usize, upper: usize) -> Self {
+        Self::from_entries(
+            n,
+            n,
+            (0..n)
+                .flat_map(|j| (j.saturating_sub(upper)..n.min(j + lower + 1)).map(move |i| (i, j))),
+        )
+    }
+
+    /// The pattern with the nonzeros of both `self` and `other`
+    pub fn union(&self, other: &Self) -> Self {
+        assert_eq!((self.nrows, self.ncols), (other.nrows, other.ncols));
+        Self::from_entries(
+            self.nrows,
+            self.ncols,
+            self.entries().chain(other.entries()),
+        )
+    }
+
+    pub fn nrows(&self) -> usize {
+        self.nrows
+    }
+
+    pub fn ncols(&self) -> usize {
+        self.ncols
+    }
+
+    /// The number of structural nonzeros
+    pub fn nnz(&self) -> usize {
+        self.row_indices.len()
+    }
+
+    /// The row indices of the nonzeros in column `j`
+    pub fn column(&self, j: usize) -> &[usize] {
+        &self.row_indices[self.col_range(j)]
+    }
+
+    /// All `(row, column)` pairs, column by column
+    pub fn entries(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.ncols).flat_map(move |j| self.column(j).iter().map(move |&i| (i, j)))
+    }
+
+    /// Where the entry at `(i, j)` is stored in the values of a
+    /// [`SparseMatrix`] with this pattern
+    pub fn position(&self, i: usize, j: usize) -> Option<usize> {
+        let range = self.col_range(j);
+        let start = range.start;
+        self.row_indices[range]
+            .binary_search(&i)
+            .ok()
+            .map(|p| start + p)
+    }
+
+    pub(crate) fn col_range(&self, j: usize) -> Range<usize> {
+        self.col_ptrs[j]..self.col_ptrs[j + 1]
+    }
+
+    /// The row of the nonzero stored at position `p`
+    pub(crate) fn row(&self, p: usize) -> usize {
+        self.row_indices[p]
+    }
+}
+
+/// A matrix in compressed sparse column form
+#[derive(Clone, Debug, PartialEq)]
+pub struct SparseMatrix<F> {
+    pattern: SparsityPattern,
+    values: Vec<F>,
+}
+
+impl<F: Scalar + Float> SparseMatrix<F> {
+    pub fn zeros(pattern: SparsityPattern) -> Self {
+        let values = vec![F::zero(); pattern.nnz()];
+        Self { pattern, values }
+    }
+
+    /// The sparse identity, e.g. as the mass matrix of a large system
+    pub fn identity(n: usize) -> Self {
+        let mut m = Self::zeros(SparsityPattern::identity(n));
+        m.values.fill(F::one());
+        m
+    }
+
+    /// Keep the entries of `dense` that lie in `pattern`
+    pub fn from_dense(pattern: SparsityPattern, dense: DMatrixView<F>) -> Self {
+        let values = pattern.entries().map(|(i, j)| dense[(i, j)]).collect();
+        Self { pattern, values }
+    }
+
+    pub fn pattern(&self) -> &SparsityPattern {
+        &self.pattern
+    }
+
+    /// The values of the nonzeros, in the order of [`SparsityPattern::entries`]
+    pub fn values(&self) -> &[F] {
+        &self.values
+    }
+
+    pub fn values_mut(&mut self) -> &mut [F] {
+        &mut self.values
+    }
+
+    pub(crate) fn parts_mut(&mut self) -> (&SparsityPattern, &mut [F]) {
+        (&self.pattern, &mut self.values)
+    }
+
+    /// The entry at `(i, j)`, which is zero if it isn't in the pattern
+    pub fn get(&self, i: usize, j: usize) -> F {
+        self.pattern
+            .position(i, j)
+            .map_or(F::zero(), |p| self.values[p])
+    }
+
+    /// Add `x` to the entry at `(i, j)`, which must be in the pattern
+    pub fn add_to(&mut self, i: usize, j: usize, x: F) {
+        let p = self
+            .pattern
+            .position(i, j)
+            .unwrap_or_else(|| panic!("entry ({i}, {j}) is not in the sparsity pattern"));
+        self.values[p] = self.values[p] + x;
+    }
+
+    /// Set `out` to `self * x`
+    pub fn mul_to(&self, x: DVectorView<F>, mut out: DVectorViewMut<F>) {
+        out.fill(F::zero());
+        for j in 0..self.pattern.ncols {
+            for p in self.pattern.col_range(j) {
+                let i = self.pattern.row_indices[p];
+                out[i] = out[i] + self.values[p] * x[j];
+            }
+        }
+    }
+
+    pub fn to_dense(&self) -> DMatrix<F> {
+        let mut dense = DMatrix::zeros(self.pattern.nrows, self.pattern.ncols);
+        for ((i, j), &x) in self.pattern.entries().zip(&self.values) {
+            dense[(i, j)] = x;
+        }
+        dense
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{OdeSystem, SolveError, SystemJacobian};
+
+use super::StepAlgorithm;
+use my_linalg::kernels::{axpby, linear_combination};
+use nalgebra::*;
+use num_traits::Float;
+
+// Dormand–Prince 5(4), as in Hairer, Nørsett, Wanner, "Solving Ordinary
+// Differential Equations I", section II.5. This is the same tableau as MATLAB's
+// ode45 and scipy's RK45.
+
+const C: [f64; 7] = [0.0, 1. / 5., 3. / 10., 4. / 5., 8. / 9., 1., 1.];
+
+const B: [f64; 7] = [
+    35. / 384.,
+    0.,
+    500. / 1113.,
+    125. / 192.,
+    -2187. / 6784.,
+    11. / 84.,
+    0.,
+];
+
+const B_TILDE: [f64; 7] = [
+    71. / 57600.,
+    0.,
+    -71. / 16695.,
+    71. / 1920.,
+    -17253. / 339200.,
+    22. / 525.,
+    -1. / 40.,
+];
+
+#[rustfmt::skip]
+const A: [[f64; 7]; 7] = [
+    [0.,                0.,                0.,               0.,             0.,              0., 0.],
+    [1. / 5.,           0.,                0.,               0.,             0.,              0., 0.],
+    [3. / 40.,          9. / 40.,          0.,               0.,             0.,              0., 0.],
+    [44. / 45.,         -56. / 15.,        32. / 9.,         0.,             0.,              0., 0.],
+    [19372. / 6561.,    -25360. / 2187.,   64448. / 6561.,   -212. / 729.,   0.,              0., 0.],
+    [9017. / 3168.,     -355. / 33.,       46732. / 5247.,   49. / 176.,     -5103. / 18656., 0., 0.],
+    B,
+];
+
+// Coefficients of the 4th order continuous extension, from `contd5` in Hairer's
+// DOPRI5 code
+const D: [f64; 7] = [
+    -12715105075. / 11282082432.,
+    0.,
+    87487479700. / 32700410799.,
+    -10690763975. / 1880347072.,
+    701980252875. / 199316789632.,
+    -1453857185. / 822651844.,
+    69997945. / 29380423.,
+];
+
+pub struct DP5Cache<F> {
+    c: [F; 7],
+    btilde: [F; 7],
+    a: [[F; 7]; 7],
+    ks: Matrix<F, Dyn, U7, VecStorage<F, Dyn, U7>>,
+    error: DVector<F>,
+    /// Where the first and the last stage of the last step were evaluated.
+    /// The last stage is f(y1), so it is the first stage of the next step.
+    k1_t: Option<F>,
+    k7_t: Option<F>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DP5;
+
+impl<F: Float + Scalar + ComplexField<RealField = F>> StepAlgorithm<F> for DP5 {
+    type Cache = DP5Cache<F>;
+    type Interpolant = Matrix<F, Dyn, U7, VecStorage<F, Dyn, U7>>;
+    type ErrorEstimate = DVector<F>;
+    type Jacobian = SystemJacobian;
+
+    fn init_cache<S: OdeSystem<F>>(&self, sys: &S) -> Self::Cache {
+        let n = sys.dimension();
+        DP5Cache {
+            c: C.map(|x| F::from(x).unwrap()),
+            btilde: B_TILDE.map(|x| F::from(x).unwrap()),
+            a: A.map(|r| r.map(|x| F::from(x).unwrap())),
+            ks: Self::Interpolant::zeros(n),
+            error: DVector::zeros(n),
+            k1_t: None,
+            k7_t: None,
+        }
+    }
+
+    fn interpolant<'a>(&self, cache: &'a Self::Cache) -> &'a Self::Interpolant {
+        &cache.ks
+    }
+
+    fn error_estimate<'a>(&self, cache: &'a Self::Cache) -> &'a Self::ErrorEstimate {
+        &cache.error
+    }
+
+    fn step_rejected(&self, cache: &mut Self::Cache) {
+        cache.k7_t = None;
+    }
+
+    fn state_modified(&self, cache: &mut Self::Cache) {
+        cache.k1_t = None;
+        cache.k7_t = None;
+    }
+
+    fn interpolate(
+        &self,
+        y0: DVectorView<F>,
+        y1: DVectorView<F>,
+        ks: &Self::Interpolant,
+        dt: F,
+        t: F,
+    ) -> DVector<F> {
+        let d = D.map(|x| F::from(x).unwrap());
+        let t1 = F::one() - t;
+        let mut y = DVector::zeros(y0.len());
+        linear_combination(y.as_mut_slice(), &d, ks.as_slice());
+        for i in 0..y0.len() {
+            let ydiff = y1[i] - y0[i];
+            let bspl = dt * ks[(i, 0)] - ydiff;
+            let cont3 = ydiff - dt * ks[(i, 6)] - bspl;
+            let cont4 = dt * y[i];
+            y[i] = y0[i] + t * (ydiff + t1 * (bspl + t * (cont3 + t1 * cont4)));
+        }
+        y
+    }
+
+    fn step<S: OdeSystem<F>>(
+        &self,
+        cache: &mut Self::Cache,
+        system: &S,
+        mut y1: DVectorViewMut<F>,
+        y0: DVectorView<F>,
+        t: F,
+        dt: F,
+    ) -> Result<(), SolveError<F>> {
+        let ks = &mut cache.ks;
+        // A step retried from the same point, after a rejection or to shorten
+        // it, still has its first stage. Otherwise the last stage of an
+        // accepted step ending here is the first one of this step.
+        if cache.k1_t != Some(t) {
+            if cache.k7_t == Some(t) {
+                let (mut k1, k7) = ks.columns_range_pair_mut(0, 6);
+                k1.copy_from(&k7);
+            } else {
+                system.vfield(ks.column_mut(0), y0, t);
+            }
+            cache.k1_t = Some(t);
+        }
+        // The last row of A is B, so the last stage is evaluated at y1
+        for s in 1..7 {
+            linear_combination(y1.as_mut_slice(), &cache.a[s][..s], ks.as_slice());
+            axpby(F::one(), y0.as_slice(), dt, y1.as_mut_slice());
+            system.vfield(ks.column_mut(s), y1.as_view(), t + cache.c[s] * dt);
+        }
+        cache.k7_t = Some(t + dt);
+        let btilde_dt = cache.btilde.map(|b| b * dt);
+        linear_combination(cache.error.as_mut_slice(), &btilde_dt, ks.as_slice());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::step_algorithm::tests::{assert_consistent, assert_order};
+
+    #[test]
+    fn tableau() {
+        assert_consistent(&A.each_ref().map(|row| &row[..]), &B, &C);
+        assert!(B_TILDE.iter().sum::<f64>().abs() < 1e-15);
+    }
+
+    #[test]
+    fn order() {
+        for n in [64, 128] {
+            assert_order(&DP5, 5, n);
+        }
+    }
+}
@@ -1,12 +1,16 @@
 use super::*;
+use crate::SystemJacobian;
 use num_traits::Float;
 
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Euler;
 
 impl<F: Float + Scalar + ComplexField<RealField = F>> StepAlgorithm<F> for Euler {
     type Cache = DVector<F>;
     type Interpolant = ();
     type ErrorEstimate = ();
+    type Jacobian = SystemJacobian;
 
     fn init_cache<S: OdeSystem<F>>(&self, sys: &S) -> Self::Cache {
         DVector::zeros(sys.dimension())
@@ -20,11 +24,19 @@ impl<F: Float + Scalar + ComplexField<RealField = F>> StepAlgorithm<F> for Euler
         y0: DVectorView<F>,
         t: F,
         dt: F,
-    ) -> ((), ()) {
+    ) -> Result<(), SolveError<F>> {
         system.vfield(cache.as_view_mut(), y0, t);
         y1.copy_from(&y0);
-        y1.axpy(dt, &cache, F::one());
-        ((), ())
+        y1.axpy(dt, cache, F::one());
+        Ok(())
+    }
+
+    fn interpolant<'a>(&self, _cache: &'a Self::Cache) -> &'a () {
+        &()
+    }
+
+    fn error_estimate<'a>(&self, _cache: &'a Self::Cache) -> &'a () {
+        &()
     }
 
     fn interpolate(
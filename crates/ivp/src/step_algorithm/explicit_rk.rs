@@ -0,0 +1,400 @@
+use crate::{OdeSystem, SolveError, SystemJacobian};
+
+use super::StepAlgorithm;
+use my_linalg::kernels::{add_linear_combination, axpby, axpy, linear_combination};
+use nalgebra::*;
+use num_traits::Float;
+
+/// The coefficients of an explicit Runge–Kutta method with `S` stages.
+///
+/// `a` must be strictly lower triangular. The embedded weights `b_hat` are
+/// only used to estimate the local error, the solution is always propagated
+/// with `b`.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "TableauData", try_from = "TableauData")
+)]
+pub struct ButcherTableau<const S: usize> {
+    a: [[f64; S]; S],
+    b: [f64; S],
+    c: [f64; S],
+    b_hat: Option<[f64; S]>,
+    dense: Option<Vec<[f64; S]>>,
+}
+
+impl<const S: usize> ButcherTableau<S> {
+    pub fn new(a: [[f64; S]; S], b: [f64; S], c: [f64; S]) -> Self {
+        for (s, row) in a.iter().enumerate() {
+            assert!(
+                row[s..].iter().all(|&x| x == 0.),
+                "the A matrix of an explicit method must be strictly lower triangular"
+            );
+        }
+        Self {
+            a,
+            b,
+            c,
+            b_hat: None,
+            dense: None,
+        }
+    }
+
+    /// Weights of the other method in an embedded pair
+    pub fn with_embedded(mut self, b_hat: [f64; S]) -> Self {
+        self.b_hat = Some(b_hat);
+        self
+    }
+
+    /// Coefficients of a continuous extension, where `dense[j][s]` is the
+    /// coefficient of θ^(j+1) in the weight of stage `s` at θ. Without these,
+    /// dense output falls back to cubic Hermite interpolation.
+    pub fn with_dense_output(mut self, dense: Vec<[f64; S]>) -> Self {
+        self.dense = Some(dense);
+        self
+    }
+}
+
+impl ButcherTableau<4> {
+    /// The classic 4th order method, without an error estimate
+    pub fn rk4() -> Self {
+        Self::new(
+            [
+                [0., 0., 0., 0.],
+                [1. / 2., 0., 0., 0.],
+                [0., 1. / 2., 0., 0.],
+                [0., 0., 1., 0.],
+            ],
+            [1. / 6., 1. / 3., 1. / 3., 1. / 6.],
+            [0., 1. / 2., 1. / 2., 1.],
+        )
+    }
+
+    /// Bogacki–Shampine 3(2)
+    pub fn bs3() -> Self {
+        Self::new(
+            [
+                [0., 0., 0., 0.],
+                [1. / 2., 0., 0., 0.],
+                [0., 3. / 4., 0., 0.],
+                [2. / 9., 1. / 3., 4. / 9., 0.],
+            ],
+            [2. / 9., 1. / 3., 4. / 9., 0.],
+            [0., 1. / 2., 3. / 4., 1.],
+        )
+        .with_embedded([7. / 24., 1. / 4., 1. / 3., 1. / 8.])
+    }
+}
+
+impl ButcherTableau<6> {
+    /// Runge–Kutta–Fehlberg 4(5), propagating the 4th order solution
+    #[rustfmt::skip]
+    pub fn fehlberg45() -> Self {
+        Self::new(
+            [
+                [0.,              0.,               0.,              0.,             0.,        0.],
+                [1. / 4.,         0.,               0.,              0.,             0.,        0.],
+                [3. / 32.,        9. / 32.,         0.,              0.,             0.,        0.],
+                [1932. / 2197.,   -7200. / 2197.,   7296. / 2197.,   0.,             0.,        0.],
+                [439. / 216.,     -8.,              3680. / 513.,    -845. / 4104.,  0.,        0.],
+                [-8. / 27.,       2.,               -3544. / 2565.,  1859. / 4104.,  -11. / 40., 0.],
+            ],
+            [25. / 216., 0., 1408. / 2565., 2197. / 4104., -1. / 5., 0.],
+            [0., 1. / 4., 3. / 8., 12. / 13., 1., 1. / 2.],
+        )
+        .with_embedded([16. / 135., 0., 6656. / 12825., 28561. / 56430., -9. / 50., 2. / 55.])
+    }
+
+    /// Cash–Karp 5(4)
+    #[rustfmt::skip]
+    pub fn cash_karp() -> Self {
+        Self::new(
+            [
+                [0.,                0.,           0.,             0.,                0.,           0.],
+                [1. / 5.,           0.,           0.,             0.,                0.,           0.],
+                [3. / 40.,          9. / 40.,     0.,             0.,                0.,           0.],
+                [3. / 10.,          -9. / 10.,    6. / 5.,        0.,                0.,           0.],
+                [-11. / 54.,        5. / 2.,      -70. / 27.,     35. / 27.,         0.,           0.],
+                [1631. / 55296.,    175. / 512.,  575. / 13824.,  44275. / 110592.,  253. / 4096., 0.],
+            ],
+            [37. / 378., 0., 250. / 621., 125. / 594., 0., 512. / 1771.],
+            [0., 1. / 5., 3. / 10., 3. / 5., 1., 7. / 8.],
+        )
+        .with_embedded([2825. / 27648., 0., 18575. / 48384., 13525. / 55296., 277. / 14336., 1. / 4.])
+    }
+}
+
+/// An explicit Runge–Kutta method given by a [`ButcherTableau`].
+///
+/// Without an embedded pair the error estimate is always zero, so this should
+/// be used with [`ConstantStep`](crate::ConstantStep).
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "ButcherTableau<S>", from = "ButcherTableau<S>")
+)]
+pub struct ExplicitRK<const S: usize> {
+    tableau: ButcherTableau<S>,
+    /// Whether the last stage is evaluated at y1, in which case Hermite
+    /// interpolation can reuse it instead of evaluating the vector field again
+    fsal: bool,
+}
+
+impl<const S: usize> ExplicitRK<S> {
+    pub fn new(tableau: ButcherTableau<S>) -> Self {
+        let fsal = S > 0 && tableau.a[S - 1] == tableau.b && tableau.c[S - 1] == 1.;
+        Self { tableau, fsal }
+    }
+
+    /// The column of the interpolant holding f(y1), for Hermite interpolation
+    fn f1_column(&self) -> usize {
+        if self.fsal { S - 1 } else { S }
+    }
+}
+
+impl<const S: usize> From<ButcherTableau<S>> for ExplicitRK<S> {
+    fn from(tableau: ButcherTableau<S>) -> Self {
+        Self::new(tableau)
+    }
+}
+
+impl<const S: usize> From<ExplicitRK<S>> for ButcherTableau<S> {
+    fn from(explicit_rk: ExplicitRK<S>) -> Self {
+        explicit_rk.tableau
+    }
+}
+
+/// A [`ButcherTableau`] as serde sees it, since serde has no impls for arrays
+/// of a generic length
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct TableauData {
+    a: Vec<Vec<f64>>,
+    b: Vec<f64>,
+    c: Vec<f64>,
+    b_hat: Option<Vec<f64>>,
+    dense: Option<Vec<Vec<f64>>>,
+}
+
+#[cfg(feature = "serde")]
+impl<const S: usize> From<ButcherTableau<S>> for TableauData {
+    fn from(tableau: ButcherTableau<S>) -> Self {
+        Self {
+            a: tableau.a.iter().map(|row| row.to_vec()).collect(),
+            b: tableau.b.to_vec(),
+            c: tableau.c.to_vec(),
+            b_hat: tableau.b_hat.map(|b_hat| b_hat.to_vec()),
+            dense: tableau
+                .dense
+                .map(|dense| dense.iter().map(|row| row.to_vec()).collect()),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<const S: usize> TryFrom<TableauData> for ButcherTableau<S> {
+    type Error = String;
+
+    fn try_from(data: TableauData) -> Result<Self, String> {
+        let row = |x: Vec<f64>| -> Result<[f64; S], String> {
+            let n = x.len();
+            x.try_into()
+                .map_err(|_| format!("expected {S} coefficients per row, got {n}"))
+        };
+        let rows = |x: Vec<Vec<f64>>| x.into_iter().map(row).collect::<Result<Vec<_>, _>>();
+        let a = rows(data.a)?;
+        let n = a.len();
+        let a: [[f64; S]; S] = a
+            .try_into()
+            .map_err(|_| format!("expected {S} rows in A, got {n}"))?;
+        if a.iter()
+            .enumerate()
+            .any(|(s, row)| row[s..].iter().any(|&x| x != 0.))
+        {
+            return Err(
+                "the A matrix of an explicit method must be strictly lower triangular".into(),
+            );
+        }
+        Ok(Self {
+            a,
+            b: row(data.b)?,
+            c: row(data.c)?,
+            b_hat: data.b_hat.map(row).transpose()?,
+            dense: data.dense.map(rows).transpose()?,
+        })
+    }
+}
+
+pub struct ExplicitRKCache<F, const S: usize> {
+    a: [[F; S]; S],
+    b: [F; S],
+    c: [F; S],
+    btilde: Option<[F; S]>,
+    ks: DMatrix<F>,
+    error: DVector<F>,
+}
+
+impl<F: Float + Scalar + ComplexField<RealField = F>, const S: usize> StepAlgorithm<F>
+    for ExplicitRK<S>
+{
+    type Cache = ExplicitRKCache<F, S>;
+    /// One column per stage, plus f(y1) when falling back to Hermite
+    /// interpolation for a method that isn't FSAL
+    type Interpolant = DMatrix<F>;
+    type ErrorEstimate = DVector<F>;
+    type Jacobian = SystemJacobian;
+
+    fn init_cache<Sys: OdeSystem<F>>(&self, sys: &Sys) -> Self::Cache {
+        let f = |x| F::from(x).unwrap();
+        let tab = &self.tableau;
+        let n = sys.dimension();
+        // Without a continuous extension, Hermite interpolation needs f(y1),
+        // which a method that isn't FSAL keeps in an extra column
+        let columns = if tab.dense.is_some() || self.fsal {
+            S
+        } else {
+            S + 1
+        };
+        ExplicitRKCache {
+            a: tab.a.map(|r| r.map(f)),
+            b: tab.b.map(f),
+            c: tab.c.map(f),
+            btilde: tab
+                .b_hat
+                .map(|b_hat| std::array::from_fn(|s| f(tab.b[s] - b_hat[s]))),
+            ks: DMatrix::zeros(n, columns),
+            error: DVector::zeros(n),
+        }
+    }
+
+    fn interpolant<'a>(&self, cache: &'a Self::Cache) -> &'a Self::Interpolant {
+        &cache.ks
+    }
+
+    fn error_estimate<'a>(&self, cache: &'a Self::Cache) -> &'a Self::ErrorEstimate {
+        &cache.error
+    }
+
+    fn interpolate(
+        &self,
+        y0: DVectorView<F>,
+        y1: DVectorView<F>,
+        ks: &Self::Interpolant,
+        dt: F,
+        t: F,
+    ) -> DVector<F> {
+        let mut y = y0.into_owned();
+        match &self.tableau.dense {
+            Some(dense) => {
+                let b_t: [F; S] = std::array::from_fn(|s| {
+                    t * dense
+                        .iter()
+                        .rev()
+                        .fold(F::zero(), |acc, row| acc * t + F::from(row[s]).unwrap())
+                });
+                let mut sum = DVector::zeros(y0.len());
+                linear_combination(sum.as_mut_slice(), &b_t, ks.as_slice());
+                axpy(dt, sum.as_slice(), y.as_mut_slice());
+            }
+            None => {
+                let one = F::one();
+                let two = F::from(2.).unwrap();
+                let f1 = self.f1_column();
+                for i in 0..y0.len() {
+                    let ydiff = y1[i] - y0[i];
+                    y[i] = (one - t) * y0[i]
+                        + t * y1[i]
+                        + t * (t - one)
+                            * ((one - two * t) * ydiff
+                                + (t - one) * dt * ks[(i, 0)]
+                                + t * dt * ks[(i, f1)]);
+                }
+            }
+        }
+        y
+    }
+
+    fn step<Sys: OdeSystem<F>>(
+        &self,
+        cache: &mut Self::Cache,
+        system: &Sys,
+        mut y1: DVectorViewMut<F>,
+        y0: DVectorView<F>,
+        t: F,
+        dt: F,
+    ) -> Result<(), SolveError<F>> {
+        let ks = &mut cache.ks;
+        for s in 0..S {
+            linear_combination(y1.as_mut_slice(), &cache.a[s][..s], ks.as_slice());
+            axpby(F::one(), y0.as_slice(), dt, y1.as_mut_slice());
+            system.vfield(ks.column_mut(s), y1.as_view(), t + cache.c[s] * dt);
+        }
+        y1.copy_from(&y0);
+        let b_dt = cache.b.map(|b| b * dt);
+        add_linear_combination(y1.as_mut_slice(), &b_dt, ks.as_slice());
+        match &cache.btilde {
+            Some(btilde) => {
+                let btilde_dt = btilde.map(|b| b * dt);
+                linear_combination(cache.error.as_mut_slice(), &btilde_dt, ks.as_slice());
+            }
+            None => cache.error.fill(F::zero()),
+        }
+        Ok(())
+    }
+
+    fn complete_interpolant<Sys: OdeSystem<F>>(
+        &self,
+        cache: &mut Self::Cache,
+        system: &Sys,
+        _y0: DVectorView<F>,
+        y1: DVectorView<F>,
+        t: F,
+        dt: F,
+    ) {
+        if self.tableau.dense.is_some() || self.fsal {
+            return;
+        }
+        system.vfield(cache.ks.column_mut(S), y1, t + dt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::step_algorithm::tests::{assert_consistent, assert_order};
+
+    fn assert_tableau<const S: usize>(tableau: &ButcherTableau<S>) {
+        assert_consistent(
+            &tableau.a.each_ref().map(|row| &row[..]),
+            &tableau.b,
+            &tableau.c,
+        );
+        if let Some(b_hat) = &tableau.b_hat {
+            assert!((b_hat.iter().sum::<f64>() - 1.).abs() < 1e-14);
+        }
+    }
+
+    #[test]
+    fn tableaus() {
+        assert_tableau(&ButcherTableau::rk4());
+        assert_tableau(&ButcherTableau::bs3());
+        assert_tableau(&ButcherTableau::fehlberg45());
+        assert_tableau(&ButcherTableau::cash_karp());
+    }
+
+    #[test]
+    fn order() {
+        for n in [32, 64] {
+            assert_order(&ExplicitRK::new(ButcherTableau::rk4()), 4, n);
+            assert_order(&ExplicitRK::new(ButcherTableau::bs3()), 3, n);
+            assert_order(&ExplicitRK::new(ButcherTableau::cash_karp()), 5, n);
+        }
+        // The error of Fehlberg's 4th order solution changes sign between 64
+        // and 128 steps
+        for n in [16, 256] {
+            assert_order(&ExplicitRK::new(ButcherTableau::fehlberg45()), 4, n);
+        }
+    }
+}
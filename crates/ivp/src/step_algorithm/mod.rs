@@ -1,22 +1,45 @@
+use crate::error::SolveError;
+use crate::jacobian::{JacobianOf, JacobianStrategy};
 use crate::system::*;
 use nalgebra::*;
 use num_traits::Float;
 
+pub mod dp5;
 pub mod euler;
+pub mod explicit_rk;
 pub mod rosenbrock23;
 pub mod tsit5;
+pub mod vern7;
+pub mod vern9;
 
+pub use dp5::*;
 pub use euler::*;
+pub use explicit_rk::*;
 pub use rosenbrock23::*;
 pub use tsit5::*;
+pub use vern7::*;
+pub use vern9::*;
 
+/// A method for taking one step of an ODE.
+///
+/// The cache owns every buffer a step needs, sized in `init_cache`, so that
+/// stepping doesn't allocate once the cache exists. A step leaves its stages
+/// and error estimate in the cache, where [`interpolant`](Self::interpolant)
+/// and [`error_estimate`](Self::error_estimate) find them, until the next
+/// step overwrites them.
 pub trait StepAlgorithm<F: Scalar + Float> {
     type Cache;
-    type Interpolant;
+    type Interpolant: Clone;
     type ErrorEstimate;
+    /// How the Jacobian of the system is computed, which decides the systems
+    /// the algorithm can step, see [`JacobianOf`]. Methods that don't use the
+    /// Jacobian set this to [`SystemJacobian`](crate::SystemJacobian), which
+    /// works for every system.
+    type Jacobian: JacobianStrategy<F>;
 
     fn init_cache<S: OdeSystem<F>>(&self, sys: &S) -> Self::Cache;
 
+    /// Step from `y0` at `t` to `y1` at `t + dt`
     fn step<S: OdeSystem<F>>(
         &self,
         cache: &mut Self::Cache,
@@ -25,7 +48,41 @@ pub trait StepAlgorithm<F: Scalar + Float> {
         y0: DVectorView<F>,
         t: F,
         dt: F,
-    ) -> (Self::Interpolant, Self::ErrorEstimate);
+    ) -> Result<(), SolveError<F>>
+    where
+        Self::Jacobian: JacobianOf<F, S>;
+
+    /// The interpolant of the last step
+    fn interpolant<'a>(&self, cache: &'a Self::Cache) -> &'a Self::Interpolant;
+
+    /// The error estimate of the last step
+    fn error_estimate<'a>(&self, cache: &'a Self::Cache) -> &'a Self::ErrorEstimate;
+
+    /// Fill in whatever the interpolant needs beyond the stages computed in
+    /// `step`, e.g. the extra stages of a high-order continuous extension.
+    ///
+    /// This is only called for accepted steps whose dense output is used, i.e.
+    /// when it is kept, a saved time falls inside the step or a continuous
+    /// callback looks for events in it, so that other steps don't pay for it.
+    fn complete_interpolant<S: OdeSystem<F>>(
+        &self,
+        _cache: &mut Self::Cache,
+        _system: &S,
+        _y0: DVectorView<F>,
+        _y1: DVectorView<F>,
+        _t: F,
+        _dt: F,
+    ) {
+    }
+
+    /// Called when the adaptive strategy rejects the step just taken, before
+    /// it is retried with a smaller dt
+    fn step_rejected(&self, _cache: &mut Self::Cache) {}
+
+    /// Called when the state is changed between steps, e.g. by a callback, so
+    /// that whatever the cache kept from the last step about the old state,
+    /// such as a first-same-as-last stage or a Jacobian, isn't used again
+    fn state_modified(&self, _cache: &mut Self::Cache) {}
 
     fn interpolate(
         &self,
@@ -36,3 +93,79 @@ pub trait StepAlgorithm<F: Scalar + Float> {
         s: F,
     ) -> DVector<F>;
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// `y' = y cos t`, with solution `exp(sin t)` for `y(0) = 1`
+    pub(crate) struct CosGrowth;
+
+    impl OdeSystem<f64> for CosGrowth {
+        fn dimension(&self) -> usize {
+            1
+        }
+
+        fn labels(&self) -> Vec<String> {
+            vec!["y".to_string()]
+        }
+
+        fn vfield(&self, mut out: DVectorViewMut<f64>, y: DVectorView<f64>, t: f64) {
+            out[0] = y[0] * t.cos();
+        }
+    }
+
+    /// The error at `t = 5` after `n` equal steps from `y(0) = 1`
+    fn global_error<SA: StepAlgorithm<f64, Jacobian: JacobianOf<f64, CosGrowth>>>(
+        step_algorithm: &SA,
+        n: usize,
+    ) -> f64 {
+        let mut cache = step_algorithm.init_cache(&CosGrowth);
+        let dt = 5. / n as f64;
+        let mut y0 = DVector::from_element(1, 1.);
+        let mut y1 = DVector::zeros(1);
+        for i in 0..n {
+            let t = i as f64 * dt;
+            step_algorithm
+                .step(
+                    &mut cache,
+                    &CosGrowth,
+                    y1.as_view_mut(),
+                    y0.as_view(),
+                    t,
+                    dt,
+                )
+                .unwrap();
+            std::mem::swap(&mut y0, &mut y1);
+        }
+        (y0[0] - f64::exp(f64::sin(5.))).abs()
+    }
+
+    /// Check that halving the step from `5 / n` shrinks the global error at least
+    /// as fast as for a method of order `order`. Before the leading error term
+    /// takes over, high order methods often converge faster than their order.
+    pub(crate) fn assert_order<SA: StepAlgorithm<f64, Jacobian: JacobianOf<f64, CosGrowth>>>(
+        step_algorithm: &SA,
+        order: u32,
+        n: usize,
+    ) {
+        let observed =
+            (global_error(step_algorithm, n) / global_error(step_algorithm, 2 * n)).log2();
+        assert!(
+            observed > order as f64 - 0.3,
+            "expected order {order}, observed {observed} from {n} steps"
+        );
+    }
+
+    /// Check that the weights `b` sum to one and that each node is the row sum
+    /// of `a`, which only holds up to rounding for decimal coefficients
+    pub(crate) fn assert_consistent(a: &[&[f64]], b: &[f64], c: &[f64]) {
+        let tol = 1e-13;
+        assert!((b.iter().sum::<f64>() - 1.).abs() < tol);
+        assert_eq!(a.len(), c.len());
+        for (s, (row, c)) in a.iter().zip(c).enumerate() {
+            let sum: f64 = row.iter().sum();
+            assert!((sum - c).abs() < tol, "row {s} sums to {sum}, not {c}");
+        }
+    }
+}
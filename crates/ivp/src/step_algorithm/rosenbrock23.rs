@@ -1,46 +1,134 @@
-use crate::OdeSystem;
+use crate::{
+    AutoSolver, JacobianOf, JacobianReuse, JacobianStrategy, LinearSolver, OdeSystem, ReuseState,
+    SolveError, SystemJacobian,
+};
 
 use super::StepAlgorithm;
+use my_linalg::kernels::{axpby, axpy};
 use nalgebra::*;
 use num_traits::Float;
 
-pub struct Rosenbrock23;
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rosenbrock23<J = SystemJacobian, L = AutoSolver> {
+    jacobian: J,
+    linear_solver: L,
+    reuse: JacobianReuse,
+}
+
+impl Rosenbrock23 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<J, L> Rosenbrock23<J, L> {
+    /// Set how the Jacobian of the system is computed
+    pub fn with_jacobian<J2>(self, jacobian: J2) -> Rosenbrock23<J2, L> {
+        Rosenbrock23 {
+            jacobian,
+            linear_solver: self.linear_solver,
+            reuse: self.reuse,
+        }
+    }
+
+    /// Set how the linear systems with `W = J - M / (dt d)` are solved
+    pub fn with_linear_solver<L2>(self, linear_solver: L2) -> Rosenbrock23<J, L2> {
+        Rosenbrock23 {
+            jacobian: self.jacobian,
+            linear_solver,
+            reuse: self.reuse,
+        }
+    }
+
+    /// Keep the Jacobian across steps. This is a W-method, so it stays second
+    /// order with an out of date Jacobian, though the error estimate gets less
+    /// reliable.
+    pub fn with_jacobian_reuse(self, reuse: JacobianReuse) -> Self {
+        Self { reuse, ..self }
+    }
+}
 
-pub struct Rosenbrock23Cache<F> {
+pub struct Rosenbrock23Cache<F, J, L> {
     e32: F,
     d: F,
-    mass_matrix: DMatrix<F>,
+    jacobian: J,
+    linear_solver: L,
+    reuse: ReuseState<F>,
     f0: DVector<F>,
     f1: DVector<F>,
     f2: DVector<F>,
+    dt_grad: DVector<F>,
+    /// `k1` and `k2`, which are also the interpolant
+    ks: [DVector<F>; 2],
+    k3: DVector<F>,
+    tmp: DVector<F>,
+    error: DVector<F>,
 }
 
-impl<F: Float + ComplexField<RealField = F>> StepAlgorithm<F> for Rosenbrock23 {
-    type Cache = Rosenbrock23Cache<F>;
-    type Interpolant = [DVector<F>; 3];
-    type ErrorEstimate = F;
+impl<F, J, L> StepAlgorithm<F> for Rosenbrock23<J, L>
+where
+    F: Float + ComplexField<RealField = F>,
+    J: JacobianStrategy<F>,
+    L: LinearSolver<F>,
+{
+    type Cache = Rosenbrock23Cache<F, J::Cache, L::Cache>;
+    type Interpolant = [DVector<F>; 2];
+    type ErrorEstimate = DVector<F>;
+    type Jacobian = J;
 
     fn init_cache<S: OdeSystem<F>>(&self, sys: &S) -> Self::Cache {
         let n = sys.dimension();
         Rosenbrock23Cache {
             e32: F::from(6. + f64::sqrt(2.0)).unwrap(),
             d: F::from(1. / (2. + f64::sqrt(2.0))).unwrap(),
-            mass_matrix: sys.mass_matrix(),
+            jacobian: self.jacobian.init_cache(sys),
+            linear_solver: self.linear_solver.init_cache(sys),
+            reuse: ReuseState::default(),
             f0: DVector::zeros(n),
             f1: DVector::zeros(n),
             f2: DVector::zeros(n),
+            dt_grad: DVector::zeros(n),
+            ks: [DVector::zeros(n), DVector::zeros(n)],
+            k3: DVector::zeros(n),
+            tmp: DVector::zeros(n),
+            error: DVector::zeros(n),
         }
     }
 
+    fn interpolant<'a>(&self, cache: &'a Self::Cache) -> &'a Self::Interpolant {
+        &cache.ks
+    }
+
+    fn error_estimate<'a>(&self, cache: &'a Self::Cache) -> &'a Self::ErrorEstimate {
+        &cache.error
+    }
+
+    fn step_rejected(&self, cache: &mut Self::Cache) {
+        cache.reuse.step_rejected();
+    }
+
+    fn state_modified(&self, cache: &mut Self::Cache) {
+        cache.reuse.state_modified();
+    }
+
     fn interpolate(
         &self,
         y0: DVectorView<F>,
-        y1: DVectorView<F>,
-        _interpolant: &Self::Interpolant,
-        _dt: F,
+        _y1: DVectorView<F>,
+        [k1, k2]: &Self::Interpolant,
+        dt: F,
         s: F,
     ) -> DVector<F> {
-        y0.scale(F::one() - s) + y1.scale(s)
+        // The second order continuous extension from Shampine and Reichelt,
+        // "The MATLAB ODE Suite", which matches y1 at s = 1
+        let two_d = F::from(2. / (2. + f64::sqrt(2.0))).unwrap();
+        let c1 = s * (F::one() - s) / (F::one() - two_d);
+        let c2 = s * (s - two_d) / (F::one() - two_d);
+        let mut y = y0.into_owned();
+        y.axpy(dt * c1, k1, F::one());
+        y.axpy(dt * c2, k2, F::one());
+        y
     }
 
     fn step<S: OdeSystem<F>>(
@@ -51,38 +139,164 @@ impl<F: Float + ComplexField<RealField = F>> StepAlgorithm<F> for Rosenbrock23 {
         y0: DVectorView<F>,
         t: F,
         dt: F,
-    ) -> (Self::Interpolant, Self::ErrorEstimate) {
+    ) -> Result<(), SolveError<F>>
+    where
+        J: JacobianOf<F, S>,
+    {
         let n = system.dimension();
-        // let d = cache.d;
         let dto2 = dt / F::from(2.).unwrap();
-        let dtd = dt * cache.d;
+        let two = F::from(2.).unwrap();
+        let Rosenbrock23Cache {
+            e32,
+            d,
+            jacobian: jacobian_cache,
+            linear_solver: solver_cache,
+            reuse,
+            f0,
+            f1,
+            f2,
+            dt_grad,
+            ks: [k1, k2],
+            k3,
+            tmp,
+            error,
+        } = cache;
+        let (e32, dtd) = (*e32, dt * *d);
         let neginvdtd = -Float::recip(dtd);
-        let mut w = DMatrix::zeros(n, n);
-        system.jacobian(w.as_view_mut(), y0, t);
-        w += cache.mass_matrix.scale(neginvdtd);
-        let wlu = w.lu();
-        system.vfield(cache.f0.as_view_mut(), y0, t);
-        // TODO: for non-autonomous system, we need f0 + dtd * dT
-        let mut k1 = wlu.solve(&cache.f0).unwrap();
+        let linear_solver = &self.linear_solver;
+        let mut new_jacobian = reuse.needs_jacobian(&self.reuse, t, dt);
+        loop {
+            if new_jacobian {
+                linear_solver.update_jacobian(
+                    solver_cache,
+                    &self.jacobian,
+                    jacobian_cache,
+                    system,
+                    y0,
+                    t,
+                );
+                reuse.jacobian_updated(t, dt);
+            }
+            if !reuse.needs_factor(dt) {
+                break;
+            }
+            if linear_solver.factor(solver_cache, system, -neginvdtd) {
+                reuse.factored(dt);
+                break;
+            }
+            // W may only be singular because the Jacobian is out of date
+            if !reuse.is_old() {
+                return Err(SolveError::SingularMatrix { t });
+            }
+            reuse.convergence_failure();
+            new_jacobian = true;
+        }
+        let mut solve = |solver_cache: &mut L::Cache, b: &mut DVector<F>| {
+            if linear_solver.solve(
+                solver_cache,
+                &self.jacobian,
+                jacobian_cache,
+                system,
+                b.as_view_mut(),
+            ) {
+                Ok(())
+            } else {
+                Err(SolveError::LinearSolveFailed { t })
+            }
+        };
+        system.vfield(f0.as_view_mut(), y0, t);
+        system.tgrad(dt_grad.as_view_mut(), y0, f0.as_view(), t);
+        k1.copy_from(f0);
+        axpy(dtd, dt_grad.as_slice(), k1.as_mut_slice());
+        solve(solver_cache, k1)?;
         k1.scale_mut(neginvdtd);
         y1.copy_from(&y0);
-        y1.axpy(dto2, &k1, F::one());
-        system.vfield(cache.f1.as_view_mut(), y1.as_view(), t + dto2);
-        let mut k2 = &cache.f1 - &cache.mass_matrix * &k1;
-        wlu.solve_mut(&mut k2);
-        k2.axpy(F::one(), &k1, neginvdtd);
+        axpy(dto2, k1.as_slice(), y1.as_mut_slice());
+        system.vfield(f1.as_view_mut(), y1.as_view(), t + dto2);
+        linear_solver.mass_mul(solver_cache, k1.as_view(), tmp.as_view_mut());
+        k2.copy_from(f1);
+        axpy(-F::one(), tmp.as_slice(), k2.as_mut_slice());
+        solve(solver_cache, k2)?;
+        axpby(F::one(), k1.as_slice(), neginvdtd, k2.as_mut_slice());
         y1.copy_from(&y0);
-        y1.axpy(dt, &k2, F::one());
-        system.vfield(cache.f2.as_view_mut(), y1.as_view(), t + dt);
-        let mut k3 = DVector::zeros(n);
+        axpy(dt, k2.as_slice(), y1.as_mut_slice());
+        system.vfield(f2.as_view_mut(), y1.as_view(), t + dt);
+        tmp.copy_from(k1);
+        axpby(e32, k2.as_slice(), two, tmp.as_mut_slice());
+        linear_solver.mass_mul(solver_cache, tmp.as_view(), k3.as_view_mut());
+        for i in 0..n {
+            k3[i] = f2[i] - k3[i] + e32 * f1[i] + two * f0[i] + dtd * dt_grad[i];
+        }
+        solve(solver_cache, k3)?;
+        let dto6 = dt / F::from(6.).unwrap();
         for i in 0..n {
-            k3[i] = cache.f2[i]
-                - cache.e32 * (k2[i] - cache.f1[i])
-                - F::from(2.).unwrap() * (k1[i] - cache.f0[i]);
+            k3[i] = neginvdtd * k3[i];
+            error[i] = dto6 * (k1[i] - two * k2[i] + k3[i]);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::step_algorithm::tests::assert_order;
+
+    /// `y0' = -y0² (1 + t)`, `y1' = y1 cos t`, whose solution from `(1, 1)` at
+    /// `t = 0` is `(1 / (1 + t + t²/2), exp(sin t))`
+    struct Forced;
+
+    impl OdeSystem<f64> for Forced {
+        fn dimension(&self) -> usize {
+            2
+        }
+
+        fn labels(&self) -> Vec<String> {
+            vec!["y0".to_string(), "y1".to_string()]
+        }
+
+        fn vfield(&self, mut out: DVectorViewMut<f64>, y: DVectorView<f64>, t: f64) {
+            out[0] = -y[0] * y[0] * (1. + t);
+            out[1] = y[1] * t.cos();
+        }
+    }
+
+    /// The norms of the local error and of the error estimate of one step of
+    /// `dt` from `t = 0`
+    fn one_step(dt: f64) -> (f64, f64) {
+        let rosenbrock = Rosenbrock23::new();
+        let mut cache = rosenbrock.init_cache(&Forced);
+        let y0 = dvector![1., 1.];
+        let mut y1 = DVector::zeros(2);
+        rosenbrock
+            .step(&mut cache, &Forced, y1.as_view_mut(), y0.as_view(), 0., dt)
+            .unwrap();
+        let exact = dvector![1. / (1. + dt + dt * dt / 2.), f64::exp(f64::sin(dt))];
+        ((y1 - exact).norm(), cache.error.norm())
+    }
+
+    #[test]
+    fn order() {
+        for n in [16, 32] {
+            assert_order(&Rosenbrock23::new(), 2, n);
+        }
+    }
+
+    #[test]
+    fn local_order_on_a_forced_problem() {
+        for dt in [0.1, 0.05] {
+            let (error, estimate) = one_step(dt);
+            let (half_error, half_estimate) = one_step(dt / 2.);
+            let error_order = (error / half_error).log2();
+            assert!(
+                (error_order - 3.).abs() < 0.3,
+                "local error of order {error_order}"
+            );
+            let estimate_order = (estimate / half_estimate).log2();
+            assert!(
+                (estimate_order - 3.).abs() < 0.3,
+                "error estimate of order {estimate_order}"
+            );
         }
-        wlu.solve_mut(&mut k3);
-        let error = EuclideanNorm
-            .norm(&(&k1 - k2.scale(F::from(2.).unwrap()) + &k3).scale(dt / F::from(6.).unwrap()));
-        ([k1, k2, k3], error)
     }
 }
@@ -1,7 +1,9 @@
-use crate::OdeSystem;
+#![allow(clippy::excessive_precision)]
+
+use crate::{OdeSystem, SolveError, SystemJacobian};
 
 use super::StepAlgorithm;
-use crunchy::unroll;
+use my_linalg::kernels::{axpby, linear_combination};
 use nalgebra::*;
 use num_traits::Float;
 
@@ -18,12 +20,12 @@ const B: [f64; 7] = [
 ];
 
 const B_TILDE: [f64; 7] = [
-    0.001780011052226,
-    0.000816434459657,
-    -0.007880878010262,
-    0.144711007173263,
-    -0.582357165452555,
-    0.458082105929187,
+    -0.001780011052226,
+    -0.000816434459657,
+    0.007880878010262,
+    -0.144711007173263,
+    0.582357165452555,
+    -0.458082105929187,
     1. / 66.,
 ];
 
@@ -52,23 +54,39 @@ pub struct Tsit5Cache<F> {
     c: [F; 7],
     btilde: [F; 7],
     a: [[F; 7]; 7],
+    ks: Matrix<F, Dyn, U7, VecStorage<F, Dyn, U7>>,
+    error: DVector<F>,
 }
 
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tsit5;
 
 impl<F: Float + Scalar + ComplexField<RealField = F>> StepAlgorithm<F> for Tsit5 {
     type Cache = Tsit5Cache<F>;
     type Interpolant = Matrix<F, Dyn, U7, VecStorage<F, Dyn, U7>>;
-    type ErrorEstimate = F;
+    type ErrorEstimate = DVector<F>;
+    type Jacobian = SystemJacobian;
 
-    fn init_cache<S: OdeSystem<F>>(&self, _sys: &S) -> Self::Cache {
+    fn init_cache<S: OdeSystem<F>>(&self, sys: &S) -> Self::Cache {
+        let n = sys.dimension();
         Tsit5Cache {
             c: C.map(|x| F::from(x).unwrap()),
             btilde: B_TILDE.map(|x| F::from(x).unwrap()),
             a: A.map(|r| r.map(|x| F::from(x).unwrap())),
+            ks: Self::Interpolant::zeros(n),
+            error: DVector::zeros(n),
         }
     }
 
+    fn interpolant<'a>(&self, cache: &'a Self::Cache) -> &'a Self::Interpolant {
+        &cache.ks
+    }
+
+    fn error_estimate<'a>(&self, cache: &'a Self::Cache) -> &'a Self::ErrorEstimate {
+        &cache.error
+    }
+
     fn interpolate(
         &self,
         y0: DVectorView<F>,
@@ -99,13 +117,9 @@ impl<F: Float + Scalar + ComplexField<RealField = F>> StepAlgorithm<F> for Tsit5
             f(-34.87065786149660974) * (t - f(1.2)) * (t - f(0.666666666666666667)) * t2,
             f(2.5) * (t - f(1.)) * (t - f(0.6)) * t2,
         ];
-        let mut y = y0.into_owned();
-        for i in 0..y0.len() {
-            y[i] = y[i]
-                + dt * (0..7)
-                    .map(|s| btilde_t[s] * ks[(i, s)])
-                    .fold(F::zero(), |x, y| x + y);
-        }
+        let mut y = DVector::zeros(y0.len());
+        linear_combination(y.as_mut_slice(), &btilde_t, ks.as_slice());
+        axpby(F::one(), y0.as_slice(), dt, y.as_mut_slice());
         y
     }
 
@@ -117,33 +131,18 @@ impl<F: Float + Scalar + ComplexField<RealField = F>> StepAlgorithm<F> for Tsit5
         y0: DVectorView<F>,
         t: F,
         dt: F,
-    ) -> (Self::Interpolant, Self::ErrorEstimate) {
-        let n = system.dimension();
-        let mut ks = Self::Interpolant::zeros(n);
-        y1.copy_from(&y0);
-        unroll! { for s in 0..7 {
+    ) -> Result<(), SolveError<F>> {
+        let ks = &mut cache.ks;
+        // The last row of A is B, so the last stage is evaluated at y1
+        for s in 0..7 {
+            linear_combination(y1.as_mut_slice(), &cache.a[s][..s], ks.as_slice());
+            axpby(F::one(), y0.as_slice(), dt, y1.as_mut_slice());
             system.vfield(ks.column_mut(s), y1.as_view(), t + cache.c[s] * dt);
-            for i in 0..n {
-                let mut dy = F::zero();
-                for j in 0..s {
-                    dy += cache.a[s][j] * ks[(i, j)];
-                }
-                y1[i] = y0[i] + dt * dy;
-            }
-        } }
-        let mut y1hat = y0.into_owned();
-        unroll! { for s in 0..7 {
-            let btilde_dt = cache.btilde[s] * dt;
-            for i in 0..n {
-                y1hat[i] = y1hat[i] + btilde_dt * ks[(i, s)];
-            }
-        }}
-        let mut error = F::zero();
-        for i in 0..n {
-            let d = y1[i] - y1hat[i];
-            error += d * d;
         }
-        error /= F::from(n).unwrap();
-        (ks, Float::sqrt(error))
+        // B_TILDE holds the differences between the weights of the two
+        // methods in the embedded pair, so this is directly y1 - y1hat
+        let btilde_dt = cache.btilde.map(|b| b * dt);
+        linear_combination(cache.error.as_mut_slice(), &btilde_dt, ks.as_slice());
+        Ok(())
     }
 }
@@ -0,0 +1,476 @@
+#![allow(clippy::excessive_precision)]
+
+use crate::{OdeSystem, SolveError, SystemJacobian};
+
+use super::StepAlgorithm;
+use my_linalg::kernels::{add_linear_combination, axpby, linear_combination};
+use nalgebra::*;
+use num_traits::Float;
+
+// Verner's "most efficient" 7(6) pair with its order 7 continuous extension,
+// in the revision of July 2024 on his website,
+// https://www.sfu.ca/~jverner/RKV76.IIa.Efficient.000003389335684.240711.FLOAT6040OnWeb
+// This is not the 2010 pair used by OrdinaryDiffEq's Vern7, whose nodes start
+// at c2 = 0.005, though it has the same stages and order.
+
+const C: [f64; 16] = [
+    0.,
+    0.069,
+    0.118,
+    0.177,
+    0.501,
+    0.773779911530533,
+    0.994,
+    0.998,
+    1.0,
+    1.0,
+    1.0,
+    0.32069197428643215,
+    0.107,
+    0.3,
+    0.549,
+    0.757,
+];
+
+const B: [f64; 10] = [
+    0.05163520172057869,
+    0.,
+    0.,
+    0.2767172535461649,
+    0.3374175285287151,
+    0.18844882678109678,
+    24.54134121634868,
+    -68.81190284469012,
+    44.416342817764885,
+    0.,
+];
+
+// Weights of the order 6 method in the embedded pair
+const B_HAT: [f64; 10] = [
+    0.050896765836929474,
+    0.,
+    0.,
+    0.2793777374763234,
+    0.3281330142746535,
+    0.2241721218186151,
+    0.7874574778015077,
+    0.,
+    0.,
+    -0.6700371172080292,
+];
+
+const B_TILDE: [f64; 10] = {
+    let mut btilde = [0.; 10];
+    let mut i = 0;
+    while i < 10 {
+        btilde[i] = B[i] - B_HAT[i];
+        i += 1;
+    }
+    btilde
+};
+
+// Lower triangular, one row per stage. Rows 10 and up are the extra stages
+// of the continuous extension, the first of which is evaluated at y1.
+const A: [&[f64]; 16] = [
+    &[],
+    &[0.069],
+    &[0.017101449275362317, 0.10089855072463769],
+    &[0.04425, 0., 0.13275],
+    &[
+        0.7353445130709566,
+        0.,
+        -2.8301606578569376,
+        2.595816144785981,
+    ],
+    &[
+        -12.21580485360408,
+        0.,
+        48.82665485823736,
+        -38.556155923199285,
+        2.7190858300965357,
+    ],
+    &[
+        108.86141887041765,
+        0.,
+        -432.4521181775778,
+        343.9115281800118,
+        -20.550411359252738,
+        1.2235824864010403,
+    ],
+    &[
+        113.47551318837385,
+        0.,
+        -450.8122021555997,
+        358.51327651900897,
+        -21.450466676484456,
+        1.2740533186059528,
+        -0.002174193904638423,
+    ],
+    &[
+        115.69962233242326,
+        0.,
+        -459.6635446100248,
+        365.5534717131746,
+        -21.885115863497848,
+        1.2987181096987215,
+        -5.318700918481884e-05,
+        -0.0030984947647318645,
+    ],
+    &[
+        124.15439356124647,
+        0.,
+        -493.23187133145973,
+        392.20862193158007,
+        -23.486415642908533,
+        1.3623229489089075,
+        -0.007051467367205771,
+        0.,
+        0.,
+    ],
+    &[
+        0.05163520172057869,
+        0.,
+        0.,
+        0.2767172535461649,
+        0.3374175285287151,
+        0.18844882678109678,
+        24.54134121634868,
+        -68.81190284469012,
+        44.416342817764885,
+        0.,
+    ],
+    &[
+        0.05595947882055416,
+        0.,
+        0.,
+        0.24812628303225093,
+        0.02103052990591148,
+        -0.010866524154540085,
+        5.710311335454033,
+        -16.597491517853864,
+        10.90440686603899,
+        0.,
+        -0.010784476956904222,
+    ],
+    &[
+        0.053651761997297646,
+        0.,
+        0.,
+        0.0982851337755206,
+        0.022361905369785468,
+        -0.0031961685942832794,
+        0.545100325730724,
+        -1.6387320020094327,
+        1.093896392979467,
+        0.,
+        -4.657483105197486e-05,
+        -0.06432077441802662,
+    ],
+    &[
+        0.019197177758478498,
+        0.,
+        0.,
+        -0.04739427173175534,
+        -0.026415225148211097,
+        7.054089167217366e-08,
+        1.5510798921016213,
+        -4.428095538025377,
+        2.883718325674217,
+        0.,
+        -0.004297481934497428,
+        0.12626443005885898,
+        0.22594262070577312,
+    ],
+    &[
+        0.06125242269008585,
+        0.,
+        0.,
+        0.25253104018343536,
+        0.15070548774131806,
+        0.010242706390190542,
+        -6.438444503804216,
+        18.44870904095404,
+        -12.035927432731748,
+        0.,
+        0.015131937488195222,
+        0.10434477052999154,
+        -0.019545469441291347,
+        0.,
+    ],
+    &[
+        0.08959466056806525,
+        0.,
+        0.,
+        0.6389214946562194,
+        0.4309120296822662,
+        0.07695592670277011,
+        -7.18989474200733,
+        21.036455896587956,
+        -13.861406033435276,
+        0.,
+        0.008282681779726606,
+        -0.2148456743069278,
+        -0.2579762402274666,
+        0.,
+        0.,
+    ],
+];
+
+// Coefficients of the continuous extension: the weight of stage i at θ is
+// θ * Σ_j BI[i][j] θ^j
+const BI: [[f64; 7]; 16] = [
+    [
+        1.0,
+        -8.50389028920865,
+        31.18234339085711,
+        -56.830479769185395,
+        52.79739831471022,
+        -22.951833027205392,
+        3.358096581752686,
+    ],
+    [0., 0., 0., 0., 0., 0., 0.],
+    [0., 0., 0., 0., 0., 0., 0.],
+    [
+        0.,
+        6.267709807177576,
+        -68.59145123448141,
+        270.10063789541806,
+        -486.23880941627164,
+        407.13998182315004,
+        -128.40135162144642,
+    ],
+    [
+        0.,
+        7.64258507762411,
+        -83.63756743442629,
+        329.34950215345714,
+        -592.8994135546816,
+        496.44958769827747,
+        -156.56727641172208,
+    ],
+    [
+        0.,
+        4.268409521381499,
+        -46.71186327091234,
+        183.9428068612487,
+        -331.13632054261063,
+        277.26876776568474,
+        -87.44335150801085,
+    ],
+    [
+        0.,
+        555.8670558189042,
+        -6083.19932240553,
+        23954.530599007394,
+        -43123.26890207834,
+        36108.19740617653,
+        -11387.585495302614,
+    ],
+    [
+        0.,
+        -1558.6055180265873,
+        17056.79070544841,
+        -67166.53412450699,
+        120914.100165743,
+        -101244.41650953855,
+        31929.853378036034,
+    ],
+    [
+        0.,
+        1006.0404398724005,
+        -11009.72698072282,
+        43354.29892541349,
+        -78046.99336079504,
+        65350.71006860325,
+        -20609.912749553514,
+    ],
+    [0., 0., 0., 0., 0., 0., 0.],
+    [
+        0.,
+        -1.6129703871907597,
+        17.72909772735325,
+        -70.34937384312195,
+        128.13500889128017,
+        -109.0734352266537,
+        35.17167283833298,
+    ],
+    [
+        0.,
+        -2.626599644929272,
+        9.920024380612826,
+        -7.644130389085102,
+        -14.864305527142132,
+        26.113902923734624,
+        -10.898891743190944,
+    ],
+    [
+        0.,
+        9.561675242924823,
+        -36.112108563035484,
+        27.82711572968802,
+        54.110896739260276,
+        -95.06308263006682,
+        39.67550348122917,
+    ],
+    [
+        0.,
+        -9.791432054831755,
+        116.67335475961902,
+        -427.0178661529826,
+        710.0693817808117,
+        -556.8214238669929,
+        166.8879855343765,
+    ],
+    [
+        0.,
+        0.07372370767192203,
+        2.611540012355412,
+        -30.67846770866969,
+        100.42859003189109,
+        -119.63655552555436,
+        47.20116948230563,
+    ],
+    [
+        0.,
+        -8.581188645336741,
+        93.07222791200057,
+        -360.9951446906584,
+        635.7596704131289,
+        -517.9168751756013,
+        158.66131018646692,
+    ],
+];
+
+pub struct Vern7Cache<F> {
+    c: [F; 16],
+    btilde: [F; 10],
+    a: [[F; 16]; 16],
+    ytmp: DVector<F>,
+    ks: Matrix<F, Dyn, U16, VecStorage<F, Dyn, U16>>,
+    error: DVector<F>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vern7;
+
+impl<F: Float + Scalar + ComplexField<RealField = F>> StepAlgorithm<F> for Vern7 {
+    type Cache = Vern7Cache<F>;
+    type Interpolant = Matrix<F, Dyn, U16, VecStorage<F, Dyn, U16>>;
+    type ErrorEstimate = DVector<F>;
+    type Jacobian = SystemJacobian;
+
+    fn init_cache<S: OdeSystem<F>>(&self, sys: &S) -> Self::Cache {
+        let f = |x| F::from(x).unwrap();
+        let mut a = [[F::zero(); 16]; 16];
+        for (s, row) in A.iter().enumerate() {
+            for (j, &x) in row.iter().enumerate() {
+                a[s][j] = f(x);
+            }
+        }
+        Vern7Cache {
+            c: C.map(f),
+            btilde: B_TILDE.map(f),
+            a,
+            ytmp: DVector::zeros(sys.dimension()),
+            ks: Self::Interpolant::zeros(sys.dimension()),
+            error: DVector::zeros(sys.dimension()),
+        }
+    }
+
+    fn interpolant<'a>(&self, cache: &'a Self::Cache) -> &'a Self::Interpolant {
+        &cache.ks
+    }
+
+    fn error_estimate<'a>(&self, cache: &'a Self::Cache) -> &'a Self::ErrorEstimate {
+        &cache.error
+    }
+
+    fn interpolate(
+        &self,
+        y0: DVectorView<F>,
+        _y1: DVectorView<F>,
+        ks: &Self::Interpolant,
+        dt: F,
+        t: F,
+    ) -> DVector<F> {
+        let bi_t = BI.map(|r| {
+            t * r
+                .iter()
+                .rev()
+                .fold(F::zero(), |acc, &x| acc * t + F::from(x).unwrap())
+        });
+        let mut y = DVector::zeros(y0.len());
+        linear_combination(y.as_mut_slice(), &bi_t, ks.as_slice());
+        axpby(F::one(), y0.as_slice(), dt, y.as_mut_slice());
+        y
+    }
+
+    fn step<S: OdeSystem<F>>(
+        &self,
+        cache: &mut Self::Cache,
+        system: &S,
+        mut y1: DVectorViewMut<F>,
+        y0: DVectorView<F>,
+        t: F,
+        dt: F,
+    ) -> Result<(), SolveError<F>> {
+        // Only the first 10 columns are filled in here, the rest are left for
+        // `complete_interpolant`
+        let ks = &mut cache.ks;
+        for s in 0..10 {
+            linear_combination(y1.as_mut_slice(), &cache.a[s][..s], ks.as_slice());
+            axpby(F::one(), y0.as_slice(), dt, y1.as_mut_slice());
+            system.vfield(ks.column_mut(s), y1.as_view(), t + cache.c[s] * dt);
+        }
+        y1.copy_from(&y0);
+        let b_dt = B.map(|b| F::from(b).unwrap() * dt);
+        add_linear_combination(y1.as_mut_slice(), &b_dt, ks.as_slice());
+        let btilde_dt = cache.btilde.map(|b| b * dt);
+        linear_combination(cache.error.as_mut_slice(), &btilde_dt, ks.as_slice());
+        Ok(())
+    }
+
+    fn complete_interpolant<S: OdeSystem<F>>(
+        &self,
+        cache: &mut Self::Cache,
+        system: &S,
+        y0: DVectorView<F>,
+        _y1: DVectorView<F>,
+        t: F,
+        dt: F,
+    ) {
+        for s in 10..16 {
+            let ytmp = cache.ytmp.as_mut_slice();
+            linear_combination(ytmp, &cache.a[s][..s], cache.ks.as_slice());
+            axpby(F::one(), y0.as_slice(), dt, ytmp);
+            let ks = cache.ks.column_mut(s);
+            system.vfield(ks, cache.ytmp.as_view(), t + cache.c[s] * dt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::step_algorithm::tests::{assert_consistent, assert_order};
+
+    #[test]
+    fn tableau() {
+        assert_consistent(&A[..10], &B, &C[..10]);
+        assert_consistent(&A, &B, &C);
+        assert!((B_HAT.iter().sum::<f64>() - 1.).abs() < 1e-13);
+        // The continuous extension ends at the solution
+        for (s, bi) in BI.iter().enumerate() {
+            let b = B.get(s).copied().unwrap_or(0.);
+            assert!((bi.iter().sum::<f64>() - b).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn order() {
+        for n in [8, 16] {
+            assert_order(&Vern7, 7, n);
+        }
+    }
+}
@@ -0,0 +1,814 @@
+#![allow(clippy::excessive_precision)]
+
+use crate::{OdeSystem, SolveError, SystemJacobian};
+
+use super::StepAlgorithm;
+use my_linalg::kernels::{add_linear_combination, axpby, linear_combination};
+use nalgebra::*;
+use num_traits::Float;
+
+// Verner's "most efficient" 9(8) pair with its order 9 continuous extension,
+// in the revision of April 2024 on his website,
+// https://www.sfu.ca/~jverner/RKV98.IIa.Efficient.00000034399.240407.BetterEfficientonWeb
+// This is not the 2010 pair used by OrdinaryDiffEq's Vern9, whose nodes start
+// at c2 = 0.03462, though it has the same stages and order.
+
+const C: [f64; 26] = [
+    0.,
+    0.03571,
+    0.09906028091267415,
+    0.1485904213690112,
+    0.6134,
+    0.2327359473605627,
+    0.5538640526394373,
+    0.6555,
+    0.491625,
+    0.06858,
+    0.253,
+    0.6620641795412046,
+    0.8309,
+    0.8998,
+    1.0,
+    1.0,
+    1.0,
+    0.737501813998881,
+    0.749,
+    0.65,
+    0.487,
+    0.0097,
+    0.138,
+    0.249,
+    0.439,
+    0.794,
+];
+
+const B: [f64; 16] = [
+    0.015006690149797247,
+    0.,
+    0.,
+    0.,
+    0.,
+    0.,
+    0.,
+    -1.0551809927463813,
+    0.2384947263782183,
+    0.12881517742829915,
+    0.22766231110462157,
+    1.2295325874375174,
+    0.04624976662810384,
+    0.13861963193662938,
+    0.030800101683194355,
+    0.,
+];
+
+// Weights of the order 8 method in the embedded pair
+const B_HAT: [f64; 16] = [
+    0.018972105324811014,
+    0.,
+    0.,
+    0.,
+    0.,
+    0.,
+    0.,
+    3.4081103145494938,
+    0.1260323883820921,
+    0.11883750634511497,
+    0.24910419978386875,
+    -3.2699662199289783,
+    0.3023798100228883,
+    0.,
+    0.,
+    0.04652989552070924,
+];
+
+const B_TILDE: [f64; 16] = {
+    let mut btilde = [0.; 16];
+    let mut i = 0;
+    while i < 16 {
+        btilde[i] = B[i] - B_HAT[i];
+        i += 1;
+    }
+    btilde
+};
+
+// Lower triangular, one row per stage. Rows 16 and up are the extra stages
+// of the continuous extension, the first of which is evaluated at y1.
+const A: [&[f64]; 26] = [
+    &[],
+    &[0.03571],
+    &[-0.03833735636677017, 0.13739763727944432],
+    &[0.0371476053422528, 0., 0.11144281602675842],
+    &[2.674764429871505, 0., -9.982382134885293, 7.921017705013789],
+    &[
+        0.05242104050577351,
+        0.,
+        0.,
+        0.17969111891759532,
+        0.0006237879371938568,
+    ],
+    &[
+        0.15924922236476322,
+        0.,
+        0.,
+        -0.4298429877241087,
+        0.06665266542726088,
+        0.757805152571522,
+    ],
+    &[
+        0.07283333333333333,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.33593445906651037,
+        0.2467322076001563,
+    ],
+    &[
+        0.0729755859375,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.33480097296993333,
+        0.11841582390506665,
+        -0.0345673828125,
+    ],
+    &[
+        0.049112136634520964,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.03983857361308652,
+        0.10696752889393549,
+        -0.021742591654586477,
+        -0.10559564748695649,
+    ],
+    &[
+        -0.027079888186412805,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.0333,
+        -0.16455260700360572,
+        0.0342826630649739,
+        0.1585264064439221,
+        0.2185234256811225,
+    ],
+    &[
+        0.055846577691088625,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.09166533166672539,
+        0.2392399655523627,
+        0.01023834712248415,
+        -0.0026793313228595426,
+        0.042356241814742845,
+        0.2253970470166604,
+    ],
+    &[
+        -0.4802510512725196,
+        0.,
+        0.,
+        0.,
+        0.,
+        -6.3596101625559305,
+        -0.2762313898040841,
+        -6.500796633979847,
+        0.5734765877040957,
+        1.3471259948681389,
+        5.936840409706221,
+        6.590346245333925,
+    ],
+    &[
+        0.3307533067671401,
+        0.,
+        0.,
+        0.,
+        0.,
+        5.956207776829962,
+        -0.48683164004815277,
+        4.462055288206771,
+        0.7410258231442072,
+        -0.7118192034575913,
+        -5.454619594516665,
+        -4.14080372924471,
+        0.20383197231903866,
+    ],
+    &[
+        -0.5847111122998945,
+        0.,
+        0.,
+        0.,
+        0.,
+        -12.41268417116267,
+        1.360245445660928,
+        -22.426105311118683,
+        -0.8828857055865458,
+        1.7701551285382304,
+        12.158096519185339,
+        22.230375204077607,
+        -0.6634483760201249,
+        0.45096237872581374,
+    ],
+    &[
+        1.9405755498106487,
+        0.,
+        0.,
+        0.,
+        0.,
+        21.977984081145564,
+        0.8230747326984729,
+        68.16441683626354,
+        -3.117097463620267,
+        -4.56884102182244,
+        -18.74190987126265,
+        -66.57711839637832,
+        1.0989155531654418,
+        0.,
+        0.,
+    ],
+    &[
+        0.015006690149797247,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        -1.0551809927463813,
+        0.2384947263782183,
+        0.12881517742829915,
+        0.22766231110462157,
+        1.2295325874375174,
+        0.04624976662810384,
+        0.13861963193662938,
+        0.030800101683194355,
+        0.,
+    ],
+    &[
+        0.01571801061417788,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.48534034526573633,
+        0.21077875689045467,
+        0.12698024130533542,
+        0.23196870145139192,
+        -0.36202147140690966,
+        0.05366106712036344,
+        -0.028060666133855498,
+        -0.0237812137271033,
+        0.,
+        0.02691804261928989,
+    ],
+    &[
+        0.015697058325222044,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.4616075242202112,
+        0.21139465166981133,
+        0.12703309171671093,
+        0.23185405502987083,
+        -0.3385266406688373,
+        0.05298251972194236,
+        -0.027504613658871878,
+        -0.02361906185395527,
+        0.,
+        0.02668458089504036,
+        0.011396834602855415,
+    ],
+    &[
+        0.014389648842912163,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        -1.2069012191237884,
+        0.25056285546393764,
+        0.13033329157021273,
+        0.2246717759263522,
+        1.3084193257819465,
+        0.002589750180376236,
+        0.008070743254562857,
+        -0.012675682553928294,
+        0.,
+        0.011291580723733216,
+        0.0342205668070975,
+        -0.1149726368734142,
+    ],
+    &[
+        0.01452348029801042,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        -0.5214243102465819,
+        0.1866698844204604,
+        0.1299316354451273,
+        0.22621410857657193,
+        0.610458263946671,
+        0.014187156070224125,
+        0.014800610544122458,
+        -0.0037114716098717747,
+        0.,
+        0.001393256979572559,
+        1.1147310206240733,
+        -1.0212085557571458,
+        -0.2795650792912341,
+    ],
+    &[
+        0.008711816186418633,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.015329932473265603,
+        -0.0019432506062880151,
+        0.0010720959505704784,
+        0.0002601233036074381,
+        -0.018177185214102193,
+        -0.0009877668338996713,
+        -0.0033323831924177566,
+        -0.0008605833352714281,
+        0.,
+        0.0013928101438866506,
+        -0.161741998789763,
+        0.1485156561306044,
+        0.02289051095253062,
+        -0.0014297771691417793,
+    ],
+    &[
+        0.027100926287141794,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.6923104986003885,
+        -0.06725243132164495,
+        0.0880844065926946,
+        0.05982566312199631,
+        -0.8251290314815524,
+        -0.048864533875083056,
+        -0.1682608371566512,
+        -0.044431705037436084,
+        0.,
+        0.0703784463943278,
+        -8.818686397504859,
+        8.02882191960392,
+        1.306591406499158,
+        -0.16248833072240146,
+        0.,
+    ],
+    &[
+        0.014664424268579612,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.18983995613991245,
+        0.037115306519075374,
+        0.13162479811995104,
+        0.14666229288226856,
+        -0.23773311624678384,
+        -0.024930712321929498,
+        -0.09427777049221307,
+        -0.027266676276909412,
+        0.,
+        0.04097659404063362,
+        -5.538725321977495,
+        4.973442175247713,
+        0.8626294449189859,
+        -0.22502139482178932,
+        0.,
+        0.,
+    ],
+    &[
+        0.0119125268992092,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        -0.6723137858130876,
+        0.1801284266825689,
+        0.13656894474493472,
+        0.21110383937989066,
+        0.7775854726244729,
+        0.023621179485059398,
+        0.0639132560758123,
+        0.011976542892461644,
+        0.,
+        -0.021886221450870318,
+        3.500218060218659,
+        -3.1957652442513735,
+        -0.5994401048600149,
+        0.011377107372277293,
+        0.,
+        0.,
+        0.,
+    ],
+    &[
+        0.011840120140746042,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        0.,
+        -0.6641263289768791,
+        0.1788914061873194,
+        0.1367562642287085,
+        0.2107758119730286,
+        0.7679184744806336,
+        0.023134968695802973,
+        0.06230605705999707,
+        0.011570990291938664,
+        0.,
+        -0.021294416010421844,
+        3.1631662510753453,
+        -2.819544872276817,
+        -0.39787690463097586,
+        0.1304821777615737,
+        0.,
+        0.,
+        0.,
+        0.,
+    ],
+];
+
+// Coefficients of the continuous extension: the weight of stage i at θ is
+// θ * Σ_j BI[i][j] θ^j
+const BI: [[f64; 9]; 26] = [
+    [
+        1.0,
+        -60.67156499096276,
+        669.4173339890965,
+        -3377.8789462251993,
+        9286.468967391047,
+        -14780.477136811021,
+        13604.993863282247,
+        -6724.705443356114,
+        1381.8679334110566,
+    ],
+    [0., 0., 0., 0., 0., 0., 0., 0., 0.],
+    [0., 0., 0., 0., 0., 0., 0., 0., 0.],
+    [0., 0., 0., 0., 0., 0., 0., 0., 0.],
+    [0., 0., 0., 0., 0., 0., 0., 0., 0.],
+    [0., 0., 0., 0., 0., 0., 0., 0., 0.],
+    [0., 0., 0., 0., 0., 0., 0., 0., 0.],
+    [
+        0.,
+        -0.5972167605668447,
+        47.93604836413167,
+        -565.7170417002333,
+        2799.6227072028587,
+        -7032.161794919679,
+        9404.154583498766,
+        -6374.661804344304,
+        1720.36933766628,
+    ],
+    [
+        0.,
+        0.13498447079600698,
+        -10.834629145944527,
+        127.86482318699343,
+        -632.7779367772188,
+        1589.4273254122554,
+        -2125.551246305557,
+        1440.817483665755,
+        -388.8423097807011,
+    ],
+    [
+        0.,
+        0.07290747606753266,
+        -5.851972901033216,
+        69.06202973876299,
+        -341.7744427162097,
+        858.4775271201373,
+        -1148.0474435798642,
+        778.2107496409214,
+        -210.02053960135393,
+    ],
+    [
+        0.,
+        0.12885348473457856,
+        -10.342520980591798,
+        122.05721106625417,
+        -604.0372032137004,
+        1517.2356375811228,
+        -2029.008844152904,
+        1375.375645376321,
+        -371.18111685013156,
+    ],
+    [
+        0.,
+        0.6958971720762387,
+        -55.85670513574831,
+        659.1926340795728,
+        -3262.2150841408466,
+        8194.11280758829,
+        -10958.039044672965,
+        7427.971576643476,
+        -2004.6325489464173,
+    ],
+    [
+        0.,
+        0.02617668058132623,
+        -2.1010907750946046,
+        24.79601256660032,
+        -122.71060391057877,
+        308.2275402436814,
+        -412.19464510006696,
+        279.4085780645545,
+        -75.40571800304909,
+    ],
+    [
+        0.,
+        0.07845665161262075,
+        -6.297381611696196,
+        74.31851847203782,
+        -367.78777470565,
+        923.818459990864,
+        -1235.428287659739,
+        837.4423716056154,
+        -226.00574311110805,
+    ],
+    [
+        0.,
+        0.01743239982411997,
+        -1.3992245634210807,
+        16.512941882067313,
+        -81.71931133065192,
+        205.2645942498128,
+        -274.50164417988054,
+        186.07256301950014,
+        -50.21655137556766,
+    ],
+    [0., 0., 0., 0., 0., 0., 0., 0., 0.],
+    [
+        0.,
+        -0.031202370347393562,
+        2.5053235138586656,
+        -29.63209944182269,
+        147.17357489919183,
+        -371.6682626297096,
+        500.80325376259674,
+        -342.9490465144388,
+        93.79845878067125,
+    ],
+    [
+        0.,
+        1.0051891846005576,
+        -80.14524762849857,
+        904.286287161854,
+        -4159.377900797261,
+        9382.948440638738,
+        -10865.074362321895,
+        6171.218731586135,
+        -1354.8611378236733,
+    ],
+    [
+        0.,
+        -1.018790304892149,
+        81.22968543433086,
+        -916.5220998408498,
+        4215.658051870923,
+        -9509.908233268861,
+        11012.088661364269,
+        -6254.720911673139,
+        1373.1936364182188,
+    ],
+    [
+        0.,
+        0.01486756383162592,
+        -1.1854132567011975,
+        13.375128087739489,
+        -61.520574820481606,
+        138.78142244983118,
+        -160.70326769519986,
+        91.27733347752088,
+        -20.039495806540547,
+    ],
+    [
+        0.,
+        -0.002611963106691749,
+        0.20825575243877978,
+        -2.3497690346644755,
+        10.808056622683038,
+        -24.381395596370474,
+        28.232668855390138,
+        -16.035783011964465,
+        3.52057837559415,
+    ],
+    [
+        0.,
+        61.75136568508868,
+        -750.1268910552884,
+        3898.685586244842,
+        -10841.822728647458,
+        17321.575664625165,
+        -15937.704924918136,
+        7853.047625862507,
+        -1605.4056977967202,
+    ],
+    [
+        0.,
+        -1.9342980422240967,
+        147.58864316656036,
+        -1181.453462187682,
+        4124.816268317402,
+        -7708.555106592447,
+        8052.34941989865,
+        -4443.023055054952,
+        1010.2115904946925,
+    ],
+    [
+        0.,
+        0.9126428307869666,
+        -71.31710609019478,
+        695.4561446075446,
+        -2620.882193436484,
+        4957.008141154351,
+        -5038.298085544127,
+        2633.3339350590786,
+        -556.2134785809537,
+    ],
+    [
+        0.,
+        -0.46544399707479917,
+        37.09099810444154,
+        -417.1382228339855,
+        1920.7587990611883,
+        -4409.960156149582,
+        5383.383503749768,
+        -3353.518501772742,
+        839.8490238379862,
+    ],
+    [
+        0.,
+        -0.11764517082552185,
+        9.481894819354299,
+        -114.91567582983194,
+        591.3193291312467,
+        -1559.7654750865815,
+        2198.545841718649,
+        -1564.5620482737284,
+        440.013778691717,
+    ],
+];
+
+pub struct Vern9Cache<F> {
+    c: [F; 26],
+    btilde: [F; 16],
+    a: [[F; 26]; 26],
+    ytmp: DVector<F>,
+    ks: Matrix<F, Dyn, U26, VecStorage<F, Dyn, U26>>,
+    error: DVector<F>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vern9;
+
+impl<F: Float + Scalar + ComplexField<RealField = F>> StepAlgorithm<F> for Vern9 {
+    type Cache = Vern9Cache<F>;
+    type Interpolant = Matrix<F, Dyn, U26, VecStorage<F, Dyn, U26>>;
+    type ErrorEstimate = DVector<F>;
+    type Jacobian = SystemJacobian;
+
+    fn init_cache<S: OdeSystem<F>>(&self, sys: &S) -> Self::Cache {
+        let f = |x| F::from(x).unwrap();
+        let mut a = [[F::zero(); 26]; 26];
+        for (s, row) in A.iter().enumerate() {
+            for (j, &x) in row.iter().enumerate() {
+                a[s][j] = f(x);
+            }
+        }
+        Vern9Cache {
+            c: C.map(f),
+            btilde: B_TILDE.map(f),
+            a,
+            ytmp: DVector::zeros(sys.dimension()),
+            ks: Self::Interpolant::zeros(sys.dimension()),
+            error: DVector::zeros(sys.dimension()),
+        }
+    }
+
+    fn interpolant<'a>(&self, cache: &'a Self::Cache) -> &'a Self::Interpolant {
+        &cache.ks
+    }
+
+    fn error_estimate<'a>(&self, cache: &'a Self::Cache) -> &'a Self::ErrorEstimate {
+        &cache.error
+    }
+
+    fn interpolate(
+        &self,
+        y0: DVectorView<F>,
+        _y1: DVectorView<F>,
+        ks: &Self::Interpolant,
+        dt: F,
+        t: F,
+    ) -> DVector<F> {
+        let bi_t = BI.map(|r| {
+            t * r
+                .iter()
+                .rev()
+                .fold(F::zero(), |acc, &x| acc * t + F::from(x).unwrap())
+        });
+        let mut y = DVector::zeros(y0.len());
+        linear_combination(y.as_mut_slice(), &bi_t, ks.as_slice());
+        axpby(F::one(), y0.as_slice(), dt, y.as_mut_slice());
+        y
+    }
+
+    fn step<S: OdeSystem<F>>(
+        &self,
+        cache: &mut Self::Cache,
+        system: &S,
+        mut y1: DVectorViewMut<F>,
+        y0: DVectorView<F>,
+        t: F,
+        dt: F,
+    ) -> Result<(), SolveError<F>> {
+        // Only the first 16 columns are filled in here, the rest are left for
+        // `complete_interpolant`
+        let ks = &mut cache.ks;
+        for s in 0..16 {
+            linear_combination(y1.as_mut_slice(), &cache.a[s][..s], ks.as_slice());
+            axpby(F::one(), y0.as_slice(), dt, y1.as_mut_slice());
+            system.vfield(ks.column_mut(s), y1.as_view(), t + cache.c[s] * dt);
+        }
+        y1.copy_from(&y0);
+        let b_dt = B.map(|b| F::from(b).unwrap() * dt);
+        add_linear_combination(y1.as_mut_slice(), &b_dt, ks.as_slice());
+        let btilde_dt = cache.btilde.map(|b| b * dt);
+        linear_combination(cache.error.as_mut_slice(), &btilde_dt, ks.as_slice());
+        Ok(())
+    }
+
+    fn complete_interpolant<S: OdeSystem<F>>(
+        &self,
+        cache: &mut Self::Cache,
+        system: &S,
+        y0: DVectorView<F>,
+        _y1: DVectorView<F>,
+        t: F,
+        dt: F,
+    ) {
+        for s in 16..26 {
+            let ytmp = cache.ytmp.as_mut_slice();
+            linear_combination(ytmp, &cache.a[s][..s], cache.ks.as_slice());
+            axpby(F::one(), y0.as_slice(), dt, ytmp);
+            let ks = cache.ks.column_mut(s);
+            system.vfield(ks, cache.ytmp.as_view(), t + cache.c[s] * dt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::step_algorithm::tests::{assert_consistent, assert_order};
+
+    #[test]
+    fn tableau() {
+        assert_consistent(&A[..16], &B, &C[..16]);
+        assert_consistent(&A, &B, &C);
+        assert!((B_HAT.iter().sum::<f64>() - 1.).abs() < 1e-13);
+        // The continuous extension ends at the solution
+        for (s, bi) in BI.iter().enumerate() {
+            let b = B.get(s).copied().unwrap_or(0.);
+            assert!((bi.iter().sum::<f64>() - b).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn order() {
+        for n in [8, 16] {
+            assert_order(&Vern9, 9, n);
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use nalgebra::*;
 use num_traits::Float;
 
+use crate::jacobian::{FiniteDifference, FiniteDifferenceCache, JacobianOf};
+use crate::sparse::{ColumnColoring, SparseMatrix, SparsityPattern};
+
 pub trait OdeSystem<F: Scalar + Float> {
     fn dimension(&self) -> usize;
 
@@ -13,7 +16,76 @@ pub trait OdeSystem<F: Scalar + Float> {
         DMatrix::identity(n, n)
     }
 
-    fn jacobian(&self, _out: DMatrixViewMut<F>, _y: DVectorView<F>, _t: F) {
-        panic!("Jacobian not implemented");
+    /// The Jacobian of `vfield` with respect to `y`. Defaults to a forward
+    /// difference, see [`FiniteDifference`], which works in `scratch`.
+    fn jacobian(
+        &self,
+        out: DMatrixViewMut<F>,
+        y: DVectorView<F>,
+        t: F,
+        scratch: &mut FiniteDifferenceCache<F>,
+    ) {
+        FiniteDifference::Forward.jacobian(scratch, self, out, y, t);
+    }
+
+    /// The nonzero pattern of the Jacobian, for systems large and sparse enough
+    /// that a dense Jacobian is too expensive. When this is given, step
+    /// algorithms that need the Jacobian use [`sparse_jacobian`] and
+    /// [`sparse_mass_matrix`] instead of their dense counterparts, and
+    /// [`AutoSolver`](crate::AutoSolver) factorizes with a sparse LU.
+    ///
+    /// [`sparse_jacobian`]: OdeSystem::sparse_jacobian
+    /// [`sparse_mass_matrix`]: OdeSystem::sparse_mass_matrix
+    fn jacobian_sparsity(&self) -> Option<SparsityPattern> {
+        None
+    }
+
+    /// `mass_matrix` for the solvers that don't store it densely, which
+    /// systems with a mass matrix other than the identity must override too
+    fn sparse_mass_matrix(&self) -> SparseMatrix<F> {
+        SparseMatrix::identity(self.dimension())
+    }
+
+    /// The Jacobian into a matrix whose pattern contains `jacobian_sparsity`.
+    /// Defaults to forward differences with the columns grouped by `coloring`,
+    /// which comes from the pattern of `out`.
+    fn sparse_jacobian(
+        &self,
+        out: &mut SparseMatrix<F>,
+        y: DVectorView<F>,
+        t: F,
+        coloring: &ColumnColoring,
+        scratch: &mut FiniteDifferenceCache<F>,
+    ) {
+        FiniteDifference::Forward.sparse_jacobian(scratch, self, coloring, out, y, t);
+    }
+
+    /// The product of the Jacobian with `v`, for matrix-free linear solvers
+    /// like [`Gmres`](crate::Gmres). `fy` is `vfield` at `(y, t)`. Defaults
+    /// to a forward difference along `v`.
+    fn jvp(
+        &self,
+        out: DVectorViewMut<F>,
+        y: DVectorView<F>,
+        fy: DVectorView<F>,
+        t: F,
+        v: DVectorView<F>,
+        scratch: &mut FiniteDifferenceCache<F>,
+    ) {
+        FiniteDifference::Forward.jvp(scratch, self, out, y, fy, t, v);
+    }
+
+    /// The partial derivative of `vfield` with respect to `t`, where `fy` is
+    /// `vfield` at `(y, t)`. Autonomous systems can override this to fill `out`
+    /// with zeros, which saves the evaluation of `vfield` in the forward
+    /// difference used by default.
+    fn tgrad(&self, mut out: DVectorViewMut<F>, y: DVectorView<F>, fy: DVectorView<F>, t: F) {
+        let dt = Float::sqrt(F::epsilon()) * Float::max(Float::abs(t), F::one());
+        // Use the step that is actually representable
+        let dt = (t + dt) - t;
+        self.vfield(out.as_view_mut(), y, t + dt);
+        for i in 0..out.len() {
+            out[i] = (out[i] - fy[i]) / dt;
+        }
     }
 }
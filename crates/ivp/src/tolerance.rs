@@ -0,0 +1,69 @@
+use my_linalg::kernels;
+use nalgebra::*;
+use num_traits::Float;
+
+/// An absolute or relative tolerance, either shared by all components of the
+/// state or given separately for each component
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "F: Scalar + serde::Serialize",
+        deserialize = "F: Scalar + serde::Deserialize<'de>"
+    ))
+)]
+pub enum Tolerance<F> {
+    Scalar(F),
+    PerComponent(DVector<F>),
+}
+
+impl<F: Scalar + Copy> Tolerance<F> {
+    pub fn get(&self, i: usize) -> F {
+        match self {
+            Tolerance::Scalar(tol) => *tol,
+            Tolerance::PerComponent(tol) => tol[i],
+        }
+    }
+}
+
+impl<F> From<F> for Tolerance<F> {
+    fn from(tol: F) -> Self {
+        Tolerance::Scalar(tol)
+    }
+}
+
+impl<F> From<DVector<F>> for Tolerance<F> {
+    fn from(tol: DVector<F>) -> Self {
+        Tolerance::PerComponent(tol)
+    }
+}
+
+/// The weighted root-mean-square norm
+///
+/// `sqrt(1/n Σᵢ (errorᵢ / scᵢ)²)` where `scᵢ = atolᵢ + rtolᵢ max(|y0ᵢ|, |y1ᵢ|)`
+///
+/// An error estimate is acceptable when this is at most one.
+pub fn weighted_rms_norm<F: Scalar + Float>(
+    error: DVectorView<F>,
+    y0: DVectorView<F>,
+    y1: DVectorView<F>,
+    atol: &Tolerance<F>,
+    rtol: &Tolerance<F>,
+) -> F {
+    let (error, y0, y1) = (error.as_slice(), y0.as_slice(), y1.as_slice());
+    match (atol, rtol) {
+        (Tolerance::Scalar(atol), Tolerance::Scalar(rtol)) => {
+            kernels::weighted_rms_norm(error, y0, y1, *atol, *rtol)
+        }
+        (Tolerance::Scalar(atol), Tolerance::PerComponent(rtol)) => {
+            kernels::weighted_rms_norm(error, y0, y1, *atol, rtol.as_slice())
+        }
+        (Tolerance::PerComponent(atol), Tolerance::Scalar(rtol)) => {
+            kernels::weighted_rms_norm(error, y0, y1, atol.as_slice(), *rtol)
+        }
+        (Tolerance::PerComponent(atol), Tolerance::PerComponent(rtol)) => {
+            kernels::weighted_rms_norm(error, y0, y1, atol.as_slice(), rtol.as_slice())
+        }
+    }
+}
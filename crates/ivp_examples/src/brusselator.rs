@@ -0,0 +1,75 @@
+use std::f64::consts::PI;
+
+use ivp::*;
+use nalgebra::*;
+use num_traits::Float;
+
+/// The one dimensional Brusselator reaction-diffusion problem from Hairer and
+/// Wanner, "Solving Ordinary Differential Equations II", discretized on `n`
+/// interior points. `u` and `v` are interleaved so that the Jacobian has two
+/// subdiagonals and two superdiagonals.
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Brusselator {
+    n: usize,
+    alpha: f64,
+}
+
+impl<F: Float + Scalar> OdeSystem<F> for Brusselator {
+    fn dimension(&self) -> usize {
+        2 * self.n
+    }
+
+    fn labels(&self) -> Vec<String> {
+        (0..self.n)
+            .flat_map(|i| [format!("u{i}"), format!("v{i}")])
+            .collect()
+    }
+
+    fn vfield(&self, mut out: DVectorViewMut<F>, y: DVectorView<F>, _t: F) {
+        let n = self.n;
+        let c = F::from(self.alpha * ((n + 1) * (n + 1)) as f64).unwrap();
+        let (one, three, four) = (F::one(), F::from(3.).unwrap(), F::from(4.).unwrap());
+        let u = |i: usize| y[2 * i];
+        let v = |i: usize| y[2 * i + 1];
+        for i in 0..n {
+            let (ul, vl) = if i == 0 {
+                (one, three)
+            } else {
+                (u(i - 1), v(i - 1))
+            };
+            let (ur, vr) = if i == n - 1 {
+                (one, three)
+            } else {
+                (u(i + 1), v(i + 1))
+            };
+            let uuv = u(i) * u(i) * v(i);
+            out[2 * i] = one + uuv - four * u(i) + c * (ul - u(i) - u(i) + ur);
+            out[2 * i + 1] = three * u(i) - uuv + c * (vl - v(i) - v(i) + vr);
+        }
+    }
+
+    fn jacobian_sparsity(&self) -> Option<SparsityPattern> {
+        Some(SparsityPattern::banded(2 * self.n, 2, 2))
+    }
+
+    fn tgrad(&self, mut out: DVectorViewMut<F>, _y: DVectorView<F>, _fy: DVectorView<F>, _t: F) {
+        out.fill(F::zero());
+    }
+}
+
+pub fn create_prob(n: usize) -> OdeProblem<f64, Brusselator> {
+    let y0 = DVector::from_fn(2 * n, |k, _| {
+        let x = (k / 2 + 1) as f64 / (n + 1) as f64;
+        if k % 2 == 0 {
+            1. + (2. * PI * x).sin()
+        } else {
+            3.
+        }
+    });
+    OdeProblem::new(
+        Brusselator { n, alpha: 1. / 50. },
+        y0,
+        TSpan::new(0.0, 10.0),
+    )
+}
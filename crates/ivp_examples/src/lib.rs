@@ -1,3 +1,4 @@
+pub mod brusselator;
 pub mod fitzhugh_nagumo;
 pub mod lotka_volterra;
 pub mod pleiades;
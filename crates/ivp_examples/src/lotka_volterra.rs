@@ -5,6 +5,7 @@ use nalgebra::*;
 use num_traits::Float;
 
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LotkaVolterra<F> {
     alpha: F,
     beta: F,
@@ -2,6 +2,7 @@ use ivp::*;
 use nalgebra::*;
 use num_traits::Float;
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pleaides {
     nplanets: usize,
 }
@@ -28,6 +29,9 @@ impl<F: Float + Scalar> OdeSystem<F> for Pleaides {
 
         out.view_mut((0, 0), (2 * n, 1))
             .copy_from(&u.view((2 * n, 0), (2 * n, 1)));
+        // The accelerations are accumulated below, so don't rely on `out`
+        // being zeroed by the caller
+        out.view_mut((2 * n, 0), (2 * n, 1)).fill(F::zero());
 
         for i in 0..n {
             for j in 0..n {
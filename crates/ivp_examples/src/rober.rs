@@ -2,6 +2,7 @@ use ivp::*;
 use nalgebra::*;
 
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rober<F> {
     k1: F,
     k2: F,
@@ -33,7 +34,20 @@ impl OdeSystem<f64> for Rober<f64> {
         ]
     }
 
-    fn jacobian(&self, mut out: DMatrixViewMut<f64>, u: DVectorView<f64>, _t: f64) {
+    fn sparse_mass_matrix(&self) -> SparseMatrix<f64> {
+        SparseMatrix::from_dense(
+            SparsityPattern::from_entries(3, 3, [(0, 0), (1, 1)]),
+            OdeSystem::<f64>::mass_matrix(self).as_view(),
+        )
+    }
+
+    fn jacobian(
+        &self,
+        mut out: DMatrixViewMut<f64>,
+        u: DVectorView<f64>,
+        _t: f64,
+        _scratch: &mut FiniteDifferenceCache<f64>,
+    ) {
         let Rober { k1, k2, k3 } = *self;
         let (_y1, y2, y3) = (u[0], u[1], u[2]);
         out[(0, 0)] = -k1;
@@ -46,6 +60,16 @@ impl OdeSystem<f64> for Rober<f64> {
         out[(2, 1)] = 1.0;
         out[(2, 2)] = 1.0;
     }
+
+    fn tgrad(
+        &self,
+        mut out: DVectorViewMut<f64>,
+        _u: DVectorView<f64>,
+        _fu: DVectorView<f64>,
+        _t: f64,
+    ) {
+        out.fill(0.0);
+    }
 }
 
 const EXAMPLE_SYS: Rober<f64> = Rober {
@@ -0,0 +1,137 @@
+//! Kernels on slices for the inner loops of the ODE solvers.
+//!
+//! As the `vector_add` benchmarks show, a loop over slices is what the compiler
+//! vectorizes, where indexing a nalgebra view element by element isn't. Each
+//! kernel adds in the same order as the obvious loop, so switching to one
+//! doesn't change results.
+
+use num_traits::Float;
+
+/// `y = y + a x`
+pub fn axpy<F: Float>(a: F, x: &[F], y: &mut [F]) {
+    assert_eq!(x.len(), y.len());
+    for (y, &x) in y.iter_mut().zip(x) {
+        *y = *y + a * x;
+    }
+}
+
+/// `y = a x + b y`
+pub fn axpby<F: Float>(a: F, x: &[F], b: F, y: &mut [F]) {
+    assert_eq!(x.len(), y.len());
+    for (y, &x) in y.iter_mut().zip(x) {
+        *y = a * x + b * *y;
+    }
+}
+
+/// `out = Σⱼ coeffs[j] xⱼ`, where the `xⱼ` are the columns of the column
+/// major matrix `columns` with as many rows as `out`, as in the stages of a
+/// Runge-Kutta method. Columns past the last coefficient are ignored.
+pub fn linear_combination<F: Float>(out: &mut [F], coeffs: &[F], columns: &[F]) {
+    combine(out, coeffs, columns, |_| F::zero(), |_, sum| sum);
+}
+
+/// `out = out + Σⱼ coeffs[j] xⱼ`, see [`linear_combination`]
+pub fn add_linear_combination<F: Float>(out: &mut [F], coeffs: &[F], columns: &[F]) {
+    combine(out, coeffs, columns, |o| o, |_, sum| sum);
+}
+
+/// `out = y + a Σⱼ coeffs[j] xⱼ`, see [`linear_combination`]
+pub fn offset_linear_combination<F: Float>(
+    out: &mut [F],
+    y: &[F],
+    a: F,
+    coeffs: &[F],
+    columns: &[F],
+) {
+    assert_eq!(y.len(), out.len());
+    let mut y = y.iter();
+    combine(
+        out,
+        coeffs,
+        columns,
+        |_| F::zero(),
+        |_, sum| *y.next().unwrap() + a * sum,
+    );
+}
+
+/// Rows of `out` summed at a time, few enough that their sums stay in L1
+const BLOCK: usize = 32;
+
+/// Set each element of `out` to `finish(o, init(o) + Σⱼ coeffs[j] xⱼ)`, where
+/// `o` is its old value. Each column is added to a block of sums on the stack
+/// in one vectorized pass, so that `out` and the columns are only read once.
+fn combine<F: Float>(
+    out: &mut [F],
+    coeffs: &[F],
+    columns: &[F],
+    init: impl Fn(F) -> F,
+    mut finish: impl FnMut(F, F) -> F,
+) {
+    let n = out.len();
+    assert!(
+        columns.len() >= coeffs.len() * n,
+        "fewer columns than coefficients"
+    );
+    let mut sums = [F::zero(); BLOCK];
+    for (b, out) in out.chunks_mut(BLOCK).enumerate() {
+        let start = b * BLOCK;
+        let sums = &mut sums[..out.len()];
+        for (s, &o) in sums.iter_mut().zip(out.iter()) {
+            *s = init(o);
+        }
+        for (j, &c) in coeffs.iter().enumerate() {
+            let x = &columns[j * n + start..j * n + start + out.len()];
+            for (s, &x) in sums.iter_mut().zip(x) {
+                *s = *s + c * x;
+            }
+        }
+        for (o, &s) in out.iter_mut().zip(sums.iter()) {
+            *o = finish(*o, s);
+        }
+    }
+}
+
+/// `sqrt(1/n Σᵢ (xᵢ / scᵢ)²)` where `scᵢ = atolᵢ + rtolᵢ max(|y0ᵢ|, |y1ᵢ|)`,
+/// the norm that error estimates are compared to one with
+pub fn weighted_rms_norm<F: Float>(
+    x: &[F],
+    y0: &[F],
+    y1: &[F],
+    atol: impl Elementwise<F>,
+    rtol: impl Elementwise<F>,
+) -> F {
+    let n = x.len();
+    let (y0, y1) = (&y0[..n], &y1[..n]);
+    let mut sum = F::zero();
+    for i in 0..n {
+        let sc = atol.get(i) + rtol.get(i) * y0[i].abs().max(y1[i].abs());
+        let d = x[i] / sc;
+        sum = sum + d * d;
+    }
+    (sum / F::from(n).unwrap()).sqrt()
+}
+
+/// The largest absolute value in `x`, or zero if it is empty
+pub fn max_abs<F: Float>(x: &[F]) -> F {
+    x.iter().fold(F::zero(), |m, &xi| m.max(xi.abs()))
+}
+
+/// One value for each element of a vector, either shared by all of them or
+/// given separately
+pub trait Elementwise<F> {
+    fn get(&self, i: usize) -> F;
+}
+
+impl<F: Float> Elementwise<F> for F {
+    #[inline]
+    fn get(&self, _i: usize) -> F {
+        *self
+    }
+}
+
+impl<F: Float> Elementwise<F> for &[F] {
+    #[inline]
+    fn get(&self, i: usize) -> F {
+        self[i]
+    }
+}
@@ -1 +1,2 @@
+pub mod kernels;
 pub mod vector_add;